@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+
+/// A text encoding that input can be decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Utf16le,
+    Utf16be,
+}
+
+impl Encoding {
+    /// Detect a byte order mark at the start of `bytes`, returning the encoding it indicates and
+    /// the length of the mark.
+    pub fn detect_bom(bytes: &[u8]) -> Option<(Self, usize)> {
+        if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+            Some((Encoding::Utf8, 3))
+        } else if bytes.starts_with(&[0xff, 0xfe]) {
+            Some((Encoding::Utf16le, 2))
+        } else if bytes.starts_with(&[0xfe, 0xff]) {
+            Some((Encoding::Utf16be, 2))
+        } else {
+            None
+        }
+    }
+
+    /// Whether a `\n` byte always means a line break, so lines can be found without decoding.
+    pub fn is_ascii_compatible(self) -> bool {
+        matches!(self, Encoding::Utf8 | Encoding::Latin1)
+    }
+
+    /// The encoded form of `\n`.
+    pub fn newline(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 | Encoding::Latin1 => b"\n",
+            Encoding::Utf16le => &[b'\n', 0],
+            Encoding::Utf16be => &[0, b'\n'],
+        }
+    }
+
+    /// Decode a line of text. Bytes that aren't valid in the encoding are never dropped; they are
+    /// written out as escapes like `\xFF` (or `\u{D800}` for unpaired UTF-16 surrogates) so they
+    /// stay visible.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => {
+                let mut text = String::with_capacity(bytes.len());
+                for chunk in bytes.utf8_chunks() {
+                    text.push_str(chunk.valid());
+                    for byte in chunk.invalid() {
+                        let _ = write!(text, "\\x{:02X}", byte);
+                    }
+                }
+                text
+            }
+            Encoding::Latin1 => bytes.iter().map(|&byte| byte as char).collect(),
+            Encoding::Utf16le | Encoding::Utf16be => {
+                let units = bytes.chunks_exact(2).map(|unit| match self {
+                    Encoding::Utf16le => u16::from_le_bytes([unit[0], unit[1]]),
+                    _ => u16::from_be_bytes([unit[0], unit[1]]),
+                });
+
+                let mut text = String::with_capacity(bytes.len() / 2);
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => text.push(c),
+                        Err(e) => {
+                            let _ = write!(text, "\\u{{{:04X}}}", e.unpaired_surrogate());
+                        }
+                    }
+                }
+                if let [.., odd] = bytes[bytes.len() / 2 * 2..] {
+                    let _ = write!(text, "\\x{:02X}", odd);
+                }
+                text
+            }
+        }
+    }
+
+    /// Decode a line, dropping its line ending (`\n` or `\r\n`) if it has one.
+    pub fn decode_line(self, bytes: &[u8]) -> String {
+        let mut line = self.decode(bytes);
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_utf8_is_escaped() {
+        assert_eq!(Encoding::Utf8.decode(b"caf\xe9 ok"), "caf\\xE9 ok");
+        assert_eq!(Encoding::Utf8.decode(b"\xff\xfe\x00bin"), "\\xFF\\xFE\0bin");
+        assert_eq!(Encoding::Utf8.decode("café".as_bytes()), "café");
+    }
+
+    #[test]
+    fn test_latin1() {
+        assert_eq!(Encoding::Latin1.decode(b"caf\xe9"), "café");
+    }
+
+    #[test]
+    fn test_utf16() {
+        let le = "héllo\r\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(Encoding::Utf16le.decode_line(&le), "héllo");
+
+        let be = [0xd8, 0x00, 0x00, b'a', 0x00];
+        assert_eq!(Encoding::Utf16be.decode(&be), "\\u{D800}a\\x00");
+    }
+
+    #[test]
+    fn test_detect_bom() {
+        assert_eq!(
+            Encoding::detect_bom(b"\xef\xbb\xbfhi"),
+            Some((Encoding::Utf8, 3))
+        );
+        assert_eq!(
+            Encoding::detect_bom(b"\xff\xfeh\x00"),
+            Some((Encoding::Utf16le, 2))
+        );
+        assert_eq!(Encoding::detect_bom(b"hi"), None);
+    }
+}
@@ -2,7 +2,10 @@ use std::{error::Error, rc::Rc, sync::RwLock, time::Duration};
 
 use crossterm::event::{self, Event, KeyCode};
 
-use crate::{state::AppAction, AppState};
+use crate::{
+    state::{AppAction, FilterKind, FilterTarget},
+    AppState,
+};
 
 pub fn poll_events(state: Rc<RwLock<AppState>>) -> Result<Option<AppAction>, Box<dyn Error>> {
     let state = state.read().unwrap();
@@ -22,20 +25,52 @@ fn handle_general_events(state: &AppState) -> Result<Option<AppAction>, Box<dyn
     let next = event::read()?;
 
     if let Event::Key(key) = next {
+        let ctrl = key.modifiers.contains(event::KeyModifiers::CONTROL);
+        // The key after `m` names the mark to set.
+        if state.awaiting_mark {
+            return Ok(Some(match key.code {
+                KeyCode::Char(mark) if !ctrl => AppAction::SetMark(mark),
+                _ => AppAction::CancelMark,
+            }));
+        }
         let action = match key.code {
+            KeyCode::Char('c') if ctrl => Some(AppAction::CancelSearch),
             KeyCode::Down => Some(AppAction::ScrollDown(1)),
             KeyCode::Up => Some(AppAction::ScrollUp(1)),
+            KeyCode::Char('j') => Some(AppAction::MoveCursor(1)),
+            KeyCode::Char('k') => Some(AppAction::MoveCursor(-1)),
+            KeyCode::Enter => Some(AppAction::OpenDetail),
             KeyCode::Esc => Some(AppAction::Quit),
             KeyCode::Char('q') => Some(AppAction::Quit),
             KeyCode::Char('t') => Some(AppAction::ScrollToLine(1)),
             KeyCode::Char(' ') => {
-                let lines = state.last_frame_height;
+                let lines = state.last_frame_height as u64;
                 Some(AppAction::ScrollDown(lines / 2))
             }
             KeyCode::Char('b') => Some(AppAction::AttachToBottom),
-            KeyCode::Char('N') => Some(AppAction::ToggleLineNumbers),
+            KeyCode::Char('#') => Some(AppAction::ToggleLineNumbers),
             KeyCode::Char('g') => Some(AppAction::OpenGoToLine),
+            KeyCode::Char('m') => Some(AppAction::AwaitMark),
+            KeyCode::Char('@') => Some(AppAction::OpenGoToTime),
+            KeyCode::Char('w') => Some(AppAction::OpenTimeRange),
             KeyCode::Char('/') => Some(AppAction::OpenSearch),
+            KeyCode::Char('n') => Some(AppAction::NextMatch),
+            KeyCode::Char('N') => Some(AppAction::PrevMatch),
+            KeyCode::Char('R') => Some(AppAction::OpenResults),
+            KeyCode::Char('&') => Some(AppAction::OpenFilter(FilterTarget::New(
+                FilterKind::Include,
+            ))),
+            KeyCode::Char(':') => Some(AppAction::OpenQuery),
+            KeyCode::Char('F') => Some(AppAction::OpenFilterPanel),
+            KeyCode::Char('f') => Some(AppAction::OpenFacetPanel),
+            KeyCode::Char('H') => Some(AppAction::OpenHistogram),
+            KeyCode::Char('L') => Some(AppAction::CycleMinLevel),
+            KeyCode::Char('T') => Some(AppAction::ToggleTableView),
+            KeyCode::Char('C') => Some(AppAction::OpenColumnPanel),
+            KeyCode::Char(c @ '1'..='9') => {
+                let source = c.to_digit(10).unwrap() as usize - 1;
+                Some(AppAction::ToggleSource(source))
+            }
             _ => None,
         };
 
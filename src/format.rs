@@ -0,0 +1,404 @@
+mod access;
+mod bracketed;
+mod custom;
+mod json;
+mod logfmt;
+mod syslog;
+
+use std::{borrow::Cow, fmt, sync::Arc};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+
+use crate::timestamp::find_timestamp;
+
+pub use custom::{default_formats_path, load_formats, CustomFormat};
+
+/// Number of lines from the start of a source that its format is worked out from.
+const SAMPLE_LINES: usize = 20;
+
+/// How severe a line is, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    /// Parse a level name in any of its common spellings, like `WARN`, `warning` or `E`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let level = match name.trim().to_ascii_lowercase().as_str() {
+            "trace" | "trc" | "t" | "verbose" => Self::Trace,
+            "debug" | "dbg" | "d" => Self::Debug,
+            "info" | "inf" | "i" | "information" | "notice" => Self::Info,
+            "warn" | "wrn" | "w" | "warning" => Self::Warn,
+            "error" | "err" | "e" => Self::Error,
+            "fatal" | "ftl" | "f" | "critical" | "crit" | "panic" | "alert" | "emerg" => {
+                Self::Fatal
+            }
+            _ => return None,
+        };
+        Some(level)
+    }
+
+    /// The numeric levels used by bunyan and pino, like `30` for info.
+    fn from_number(number: u64) -> Option<Self> {
+        let level = match number {
+            10 => Self::Trace,
+            20 => Self::Debug,
+            30 => Self::Info,
+            40 => Self::Warn,
+            50 => Self::Error,
+            60 => Self::Fatal,
+            _ => return None,
+        };
+        Some(level)
+    }
+
+    /// The next minimum level to show when cycling through them: each level from debug up,
+    /// then back to showing everything.
+    pub fn next_threshold(threshold: Option<Level>) -> Option<Level> {
+        match threshold {
+            None | Some(Self::Trace) => Some(Self::Debug),
+            Some(Self::Debug) => Some(Self::Info),
+            Some(Self::Info) => Some(Self::Warn),
+            Some(Self::Warn) => Some(Self::Error),
+            Some(Self::Error) => Some(Self::Fatal),
+            Some(Self::Fatal) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+            Self::Fatal => "FATAL",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The fields parsed out of a line of a known format.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fields {
+    pub timestamp: Option<NaiveDateTime>,
+    pub level: Option<Level>,
+    /// The part of the program the line came from, like a logger name or syslog tag.
+    pub component: Option<String>,
+    pub message: Option<String>,
+    /// Any other fields, in the order they appear in the line.
+    pub extra: Vec<(String, String)>,
+}
+
+impl Fields {
+    /// Sort key/value pairs into fields by their names, using the names structured loggers
+    /// commonly give the standard fields.
+    fn from_pairs(pairs: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut fields = Self::default();
+        for (key, value) in pairs {
+            match key.to_ascii_lowercase().as_str() {
+                "timestamp" | "time" | "ts" | "@timestamp" | "datetime" | "date" | "t"
+                    if fields.timestamp.is_none() =>
+                {
+                    fields.timestamp = parse_timestamp(&value);
+                    if fields.timestamp.is_none() {
+                        fields.extra.push((key, value));
+                    }
+                }
+                "level" | "lvl" | "severity" | "loglevel" | "levelname" | "log.level"
+                    if fields.level.is_none() =>
+                {
+                    fields.level = Level::parse(&value)
+                        .or_else(|| value.parse().ok().and_then(Level::from_number));
+                    if fields.level.is_none() {
+                        fields.extra.push((key, value));
+                    }
+                }
+                "component" | "logger" | "logger_name" | "module" | "service" | "target"
+                | "name" | "source"
+                    if fields.component.is_none() =>
+                {
+                    fields.component = Some(value)
+                }
+                "message" | "msg" | "@message" | "text" | "event" if fields.message.is_none() => {
+                    fields.message = Some(value)
+                }
+                _ => fields.extra.push((key, value)),
+            }
+        }
+        fields
+    }
+
+    /// Look up a field by name. The standard fields are called `timestamp`, `level`,
+    /// `component` and `message`; any other name is looked up in `extra`.
+    pub fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        match name {
+            "timestamp" => self
+                .timestamp
+                .map(|time| Cow::Owned(time.format("%Y-%m-%d %H:%M:%S%.3f").to_string())),
+            "level" => self.level.map(|level| Cow::Borrowed(level.name())),
+            "component" => self.component.as_deref().map(Cow::Borrowed),
+            "message" => self.message.as_deref().map(Cow::Borrowed),
+            _ => self
+                .extra
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| Cow::Borrowed(value.as_str())),
+        }
+    }
+}
+
+/// Parse a timestamp in any of the formats `find_timestamp` knows, or a Unix time in seconds or
+/// milliseconds.
+fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        // Anything past the year 5000 in seconds is more likely to be milliseconds.
+        let millis = if seconds > 1e11 {
+            seconds
+        } else {
+            seconds * 1000.0
+        };
+        let time = DateTime::from_timestamp_millis(millis as i64)?;
+        return Some(time.with_timezone(&Local).naive_local());
+    }
+    find_timestamp(value)
+}
+
+#[derive(Debug, Clone)]
+pub enum LogFormat {
+    /// One JSON object per line.
+    Json,
+    /// `key=value` pairs, with values quoted if they have spaces in them.
+    Logfmt,
+    /// BSD syslog, like `Jan 15 10:23:45 host sshd[42]: message`.
+    Syslog,
+    /// Apache and Nginx access logs, in the common or combined format.
+    AccessLog,
+    /// `[LEVEL] [component] message`, optionally after a timestamp.
+    Bracketed,
+    /// A format declared in the formats file.
+    Custom(Arc<CustomFormat>),
+}
+
+impl PartialEq for LogFormat {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
+impl LogFormat {
+    /// The built-in formats, in the order they're preferred in when several fit a source equally
+    /// well.
+    const BUILT_IN: [LogFormat; 5] = [
+        Self::Json,
+        Self::AccessLog,
+        Self::Syslog,
+        Self::Bracketed,
+        Self::Logfmt,
+    ];
+
+    /// Every format a source could be detected as, most preferred first: the formats from the
+    /// formats file and then the built-in ones, ordered by priority. Built-in formats have a
+    /// priority of 0.
+    pub fn candidates(custom: Vec<CustomFormat>) -> Vec<LogFormat> {
+        let mut candidates = custom
+            .into_iter()
+            .map(|format| Self::Custom(Arc::new(format)))
+            .chain(Self::BUILT_IN)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|format| std::cmp::Reverse(format.priority()));
+        candidates
+    }
+
+    fn priority(&self) -> i32 {
+        match self {
+            Self::Custom(format) => format.priority,
+            _ => 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Json => "json",
+            Self::Logfmt => "logfmt",
+            Self::Syslog => "syslog",
+            Self::AccessLog => "access",
+            Self::Bracketed => "bracketed",
+            Self::Custom(format) => &format.name,
+        }
+    }
+
+    /// Parse a line, or return `None` if it isn't in this format.
+    pub fn parse(&self, line: &str) -> Option<Fields> {
+        match self {
+            Self::Json => json::parse(line),
+            Self::Logfmt => logfmt::parse(line),
+            Self::Syslog => syslog::parse(line),
+            Self::AccessLog => access::parse(line),
+            Self::Bracketed => bracketed::parse(line),
+            Self::Custom(format) => format.parse(line),
+        }
+    }
+
+    /// The one of `candidates` that parses the most of `lines`, as long as it parses at least
+    /// half of them. Earlier candidates win ties. Blank lines aren't counted.
+    pub fn detect<'a>(
+        candidates: &[LogFormat],
+        lines: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Self> {
+        let lines = lines
+            .into_iter()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+        let (format, parsed) = candidates
+            .iter()
+            .map(|format| {
+                let parsed = lines
+                    .iter()
+                    .filter(|line| format.parse(line).is_some())
+                    .count();
+                (format, parsed)
+            })
+            // `max_by_key` picks the last of equals, so go backwards to prefer earlier formats.
+            .rev()
+            .max_by_key(|&(_, parsed)| parsed)?;
+        (parsed > 0 && parsed * 2 >= lines.len()).then(|| format.clone())
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Works out the format of a source from its first lines. Until enough lines have been seen to
+/// be sure, the guess is revised with each one, so the very first lines of a source are parsed
+/// with the best guess at the time they arrive.
+#[derive(Debug)]
+pub struct FormatDetector {
+    /// The formats to choose from, or `None` if the format was given rather than detected.
+    candidates: Option<Arc<[LogFormat]>>,
+    samples: Vec<String>,
+    format: Option<LogFormat>,
+}
+
+impl Default for FormatDetector {
+    fn default() -> Self {
+        Self::new(Arc::new(LogFormat::BUILT_IN))
+    }
+}
+
+impl FormatDetector {
+    pub fn new(candidates: Arc<[LogFormat]>) -> Self {
+        Self {
+            candidates: Some(candidates),
+            samples: Vec::new(),
+            format: None,
+        }
+    }
+
+    /// A detector that has already settled on `format`.
+    pub fn fixed(format: LogFormat) -> Self {
+        Self {
+            candidates: None,
+            samples: Vec::new(),
+            format: Some(format),
+        }
+    }
+
+    /// Number of lines seen so far, up to the number needed to settle on a format.
+    pub fn sampled(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.candidates.is_none() || self.samples.len() >= SAMPLE_LINES
+    }
+
+    /// Take a line from the source into account, if the format isn't settled yet.
+    pub fn sample(&mut self, line: &str) {
+        let Some(candidates) = self.candidates.as_ref().filter(|_| !self.is_settled()) else {
+            return;
+        };
+        self.samples.push(line.to_string());
+        self.format = LogFormat::detect(candidates, self.samples.iter().map(String::as_str));
+        if self.is_settled() {
+            self.samples.shrink_to_fit();
+        }
+    }
+
+    pub fn format(&self) -> Option<&LogFormat> {
+        self.format.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let detect =
+            |lines: &[&str]| LogFormat::detect(&LogFormat::BUILT_IN, lines.iter().copied());
+        assert_eq!(
+            detect(&[
+                r#"{"level":"info","msg":"started"}"#,
+                "",
+                r#"{"level":"error","msg":"failed","err":"timeout"}"#,
+            ]),
+            Some(LogFormat::Json)
+        );
+        assert_eq!(
+            detect(&[
+                "[INFO ] [api] Request processed in slow response (id: 12)",
+                "[ERROR] [database] Connection timed out",
+                "  at frame 1",
+            ]),
+            Some(LogFormat::Bracketed)
+        );
+        assert_eq!(
+            detect(&[r#"time=2024-01-15T10:23:45Z level=warn msg="disk full" pct=99"#]),
+            Some(LogFormat::Logfmt)
+        );
+        assert_eq!(detect(&["just", "some", "text"]), None);
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let pairs = [
+            ("ts", "1705314225"),
+            ("level", "30"),
+            ("logger", "http"),
+            ("msg", "ok"),
+            ("status", "200"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let fields = Fields::from_pairs(pairs);
+
+        assert!(fields.timestamp.is_some());
+        assert_eq!(fields.level, Some(Level::Info));
+        assert_eq!(fields.get("component").as_deref(), Some("http"));
+        assert_eq!(fields.get("message").as_deref(), Some("ok"));
+        assert_eq!(fields.get("status").as_deref(), Some("200"));
+        assert_eq!(fields.get("missing"), None);
+        assert_eq!(
+            fields.extra,
+            vec![("status".to_string(), "200".to_string())]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{
+    format::{Fields, Level},
+    timestamp::find_timestamp,
+};
+
+/// The Common Log Format, optionally followed by the referer and user agent of the Combined Log
+/// Format.
+static ACCESS_LOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?x)
+        ^(?P<host>\S+)\ \S+\ (?P<user>\S+)
+        \ \[(?P<timestamp>[^\]]+)\]
+        \ "(?P<request>(?P<method>[A-Z]+)\ (?P<path>\S+)(?:\ [^"]*)?|[^"]*)"
+        \ (?P<status>\d{3})\ (?P<size>\d+|-)
+        (?:\ "(?P<referer>[^"]*)"\ "(?P<agent>[^"]*)")?"#,
+    )
+    .unwrap()
+});
+
+pub fn parse(line: &str) -> Option<Fields> {
+    let captures = ACCESS_LOG.captures(line)?;
+    let status = &captures["status"];
+
+    let extra = [
+        "host", "user", "method", "path", "status", "size", "referer", "agent",
+    ]
+    .into_iter()
+    .filter_map(|name| Some((name.to_string(), captures.name(name)?.as_str().to_string())))
+    .collect();
+    Some(Fields {
+        timestamp: find_timestamp(&captures["timestamp"]),
+        level: Some(match status.as_bytes()[0] {
+            b'5' => Level::Error,
+            b'4' => Level::Warn,
+            _ => Level::Info,
+        }),
+        component: None,
+        message: Some(captures["request"].to_string()),
+        extra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let line = r#"10.0.0.1 - alice [15/Jan/2024:10:23:45 +0000] "GET /api/users?id=7 HTTP/1.1" 404 153 "-" "curl/8.4.0""#;
+        let fields = parse(line).unwrap();
+        assert!(fields.timestamp.is_some());
+        assert_eq!(fields.level, Some(Level::Warn));
+        assert_eq!(
+            fields.message.as_deref(),
+            Some("GET /api/users?id=7 HTTP/1.1")
+        );
+        assert_eq!(
+            fields.extra[3],
+            ("path".to_string(), "/api/users?id=7".to_string())
+        );
+        assert_eq!(
+            fields.extra[7],
+            ("agent".to_string(), "curl/8.4.0".to_string())
+        );
+
+        assert!(parse(r#"10.0.0.1 - - [15/Jan/2024:10:23:45 +0000] "-" 400 0"#).is_some());
+    }
+}
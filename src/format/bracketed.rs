@@ -0,0 +1,58 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{
+    format::{Fields, Level},
+    timestamp::find_timestamp,
+};
+
+/// `[LEVEL] [component] message`, as written by `test/generate.sh` and many hand-rolled loggers,
+/// optionally after a timestamp. The component is optional too.
+static BRACKETED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        ^(?:(?P<timestamp>\d{4}[-/]\d{2}[-/]\d{2}[T\ ][\d:.,]+(?:Z|\ ?[+-]\d{2}:?\d{2})?)\s+)?
+        \[(?P<level>[A-Za-z]+)\s*\]
+        \s*(?:\[(?P<component>[^\]]+)\]\s*)?
+        (?P<message>.*)$",
+    )
+    .unwrap()
+});
+
+pub fn parse(line: &str) -> Option<Fields> {
+    let captures = BRACKETED.captures(line)?;
+    Some(Fields {
+        timestamp: captures
+            .name("timestamp")
+            .and_then(|timestamp| find_timestamp(timestamp.as_str())),
+        level: Some(Level::parse(&captures["level"])?),
+        component: captures
+            .name("component")
+            .map(|component| component.as_str().trim().to_string()),
+        message: Some(captures["message"].to_string()),
+        extra: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let fields = parse("[WARN ] [scheduler] Rate limit exceeded slow request (id: 4)").unwrap();
+        assert_eq!(fields.level, Some(Level::Warn));
+        assert_eq!(fields.component.as_deref(), Some("scheduler"));
+        assert_eq!(
+            fields.message.as_deref(),
+            Some("Rate limit exceeded slow request (id: 4)")
+        );
+
+        let fields = parse("2024-01-15 10:23:45,120 [error] disk full").unwrap();
+        assert!(fields.timestamp.is_some());
+        assert_eq!(fields.component, None);
+
+        assert!(parse("[not-a-level] hello").is_none());
+    }
+}
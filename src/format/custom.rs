@@ -0,0 +1,256 @@
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    format::{Fields, Level},
+    timestamp::find_timestamp,
+};
+
+/// The capture groups that fill in the standard fields. Any other named group is an extra field.
+const TIMESTAMP_GROUP: &str = "ts";
+const LEVEL_GROUP: &str = "level";
+const COMPONENT_GROUP: &str = "component";
+const MESSAGE_GROUP: &str = "msg";
+
+/// A format declared in the formats file, like:
+///
+/// ```toml
+/// [formats.billing]
+/// regex = '^(?P<ts>\S+ \S+) (?P<level>\w+) \[(?P<component>[^\]]+)\] (?P<msg>.*)$'
+/// timestamp = "%Y-%m-%d %H:%M:%S%.3f"
+/// sample = "2024-01-15 10:23:45.123 INFO [invoices] invoice created"
+/// priority = 10
+/// ```
+#[derive(Debug)]
+pub struct CustomFormat {
+    pub name: String,
+    regex: Regex,
+    /// A `strftime`-style format for the `ts` group. Without one, any timestamp format `cant`
+    /// recognizes is accepted.
+    timestamp: Option<String>,
+    /// Formats with a higher priority are preferred when detecting the format of a source.
+    pub priority: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormatsFile {
+    /// Each entry is read on its own, so one that's invalid doesn't stop the rest from loading.
+    #[serde(default)]
+    formats: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormatEntry {
+    regex: String,
+    timestamp: Option<String>,
+    /// A line in the format, checked against the rest of the entry when the file is loaded.
+    sample: Option<String>,
+    #[serde(default)]
+    priority: i32,
+}
+
+impl CustomFormat {
+    pub fn parse(&self, line: &str) -> Option<Fields> {
+        let captures = self.regex.captures(line)?;
+        let mut fields = Fields::default();
+
+        for name in self.regex.capture_names().flatten() {
+            let Some(value) = captures.name(name).map(|m| m.as_str()) else {
+                continue;
+            };
+            match name {
+                TIMESTAMP_GROUP => fields.timestamp = Some(self.parse_timestamp(value)?),
+                LEVEL_GROUP => fields.level = Some(Level::parse(value)?),
+                COMPONENT_GROUP => fields.component = Some(value.to_string()),
+                MESSAGE_GROUP => fields.message = Some(value.to_string()),
+                _ => fields.extra.push((name.to_string(), value.to_string())),
+            }
+        }
+        Some(fields)
+    }
+
+    fn parse_timestamp(&self, value: &str) -> Option<NaiveDateTime> {
+        let Some(format) = self.timestamp.as_deref() else {
+            return find_timestamp(value);
+        };
+        // A format with a UTC offset in it gives a time that has to be converted to local time.
+        DateTime::parse_from_str(value, format)
+            .map(|time| time.with_timezone(&Local).naive_local())
+            .or_else(|_| NaiveDateTime::parse_from_str(value, format))
+            .ok()
+    }
+
+    fn from_entry(name: String, entry: FormatEntry) -> Result<Self, String> {
+        let regex = Regex::new(&entry.regex)
+            .map_err(|e| format!("format `{}` has an invalid regex: {}", name, e))?;
+        let format = Self {
+            name,
+            regex,
+            timestamp: entry.timestamp,
+            priority: entry.priority,
+        };
+
+        if let Some(sample) = entry.sample.as_deref() {
+            format.check_sample(sample)?;
+        }
+        Ok(format)
+    }
+
+    /// Make sure `sample` parses, saying which part of it doesn't if it can't.
+    fn check_sample(&self, sample: &str) -> Result<(), String> {
+        let fail = |problem: String| format!("format `{}`: {}", self.name, problem);
+        let captures = self
+            .regex
+            .captures(sample)
+            .ok_or_else(|| fail("the regex doesn't match the sample line".to_string()))?;
+
+        if let Some(ts) = captures.name(TIMESTAMP_GROUP) {
+            if self.parse_timestamp(ts.as_str()).is_none() {
+                return Err(fail(match self.timestamp.as_deref() {
+                    Some(format) => format!(
+                        "`{}` in the sample line doesn't match the timestamp format `{}`",
+                        ts.as_str(),
+                        format
+                    ),
+                    None => format!(
+                        "`{}` in the sample line isn't a timestamp cant recognizes; give a \
+                         `timestamp` format for it",
+                        ts.as_str()
+                    ),
+                }));
+            }
+        }
+        if let Some(level) = captures.name(LEVEL_GROUP) {
+            if Level::parse(level.as_str()).is_none() {
+                return Err(fail(format!(
+                    "`{}` in the sample line isn't a level cant recognizes",
+                    level.as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/cant/formats.toml`, falling back to `~/.config/cant/formats.toml`.
+pub fn default_formats_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".config")))?;
+    Some(config_home.join("cant").join("formats.toml"))
+}
+
+/// Load the formats declared in the file at `path`, along with what was wrong with any that
+/// couldn't be loaded, which are left out. A missing file declares none.
+pub fn load_formats(path: &Path) -> (Vec<CustomFormat>, Vec<String>) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return (Vec::new(), Vec::new()),
+        Err(e) => return (Vec::new(), vec![format!("{}: {}", path.display(), e)]),
+    };
+    let (formats, problems) = parse_formats(&contents);
+    let problems = problems
+        .into_iter()
+        .map(|problem| format!("{}: {}", path.display(), problem.trim_end()))
+        .collect();
+    (formats, problems)
+}
+
+fn parse_formats(contents: &str) -> (Vec<CustomFormat>, Vec<String>) {
+    let file: FormatsFile = match toml::from_str(contents) {
+        Ok(file) => file,
+        Err(e) => return (Vec::new(), vec![e.to_string()]),
+    };
+
+    let mut formats = Vec::new();
+    let mut problems = Vec::new();
+    for (name, entry) in file.formats {
+        let format = FormatEntry::deserialize(entry)
+            .map_err(|e| format!("format `{}`: {}", name, e))
+            .and_then(|entry| CustomFormat::from_entry(name, entry));
+        match format {
+            Ok(format) => formats.push(format),
+            Err(problem) => problems.push(problem),
+        }
+    }
+    (formats, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: &str = r#"
+        [formats.billing]
+        regex = '^(?P<ts>\S+ \S+) (?P<level>\w+) \[(?P<component>[^\]]+)\] (?P<msg>.*?)(?: tenant=(?P<tenant>\w+))?$'
+        timestamp = "%Y-%m-%d %H:%M:%S%.3f"
+        sample = "2024-01-15 10:23:45.123 INFO [invoices] invoice created tenant=acme"
+        priority = 10
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let (formats, problems) = parse_formats(FORMATS);
+        assert!(problems.is_empty());
+        let billing = &formats[0];
+        assert_eq!(billing.name, "billing");
+        assert_eq!(billing.priority, 10);
+
+        let fields = billing
+            .parse("2024-01-15 10:23:45.123 WARN [payments] card declined tenant=acme")
+            .unwrap();
+        assert_eq!(
+            fields.timestamp,
+            NaiveDateTime::parse_from_str("2024-01-15 10:23:45.123", "%Y-%m-%d %H:%M:%S%.3f").ok()
+        );
+        assert_eq!(fields.level, Some(Level::Warn));
+        assert_eq!(fields.component.as_deref(), Some("payments"));
+        assert_eq!(fields.message.as_deref(), Some("card declined"));
+        assert_eq!(
+            fields.extra,
+            vec![("tenant".to_string(), "acme".to_string())]
+        );
+
+        assert!(billing
+            .parse("2024-01-15 10:23:45.123 LOUD [payments] not a level")
+            .is_none());
+        assert!(billing
+            .parse("15/01/2024 10:23:45.123 INFO [payments] wrong date order")
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_formats() {
+        let error = |contents: &str| {
+            let (formats, problems) = parse_formats(contents);
+            assert!(formats.is_empty());
+            problems.join("\n")
+        };
+        assert!(error("[formats.x]\nregex = '(unclosed'").contains("invalid regex"));
+        assert!(error("[formats.x]\nregex = 'a'\nsample = 'b'").contains("doesn't match"));
+        assert!(
+            error("[formats.x]\nregex = '(?P<level>\\w+) '\nsample = 'LOUD hello'")
+                .contains("isn't a level")
+        );
+        assert!(error("[formats.x]\nregx = 'a'").contains("unknown field"));
+        assert!(error("[formats.x\nregex = 'a'").contains("invalid table header"));
+
+        // The formats that are valid still load.
+        let (formats, problems) =
+            parse_formats(&format!("{}\n[formats.typo]\nregx = 'a'", FORMATS));
+        assert_eq!(formats.len(), 1);
+        assert_eq!(formats[0].name, "billing");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("format `typo`: unknown field"));
+    }
+}
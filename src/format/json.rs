@@ -0,0 +1,43 @@
+use serde_json::{Map, Value};
+
+use crate::format::Fields;
+
+pub fn parse(line: &str) -> Option<Fields> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    let object = serde_json::from_str::<Map<String, Value>>(line).ok()?;
+    let pairs = object.into_iter().map(|(key, value)| {
+        let value = match value {
+            Value::String(s) => s,
+            value => value.to_string(),
+        };
+        (key, value)
+    });
+    Some(Fields::from_pairs(pairs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Level;
+
+    #[test]
+    fn test_parse() {
+        let fields = parse(
+            r#"{"time":"2024-01-15T10:23:45Z","level":"error","msg":"failed","req":{"id":7}}"#,
+        )
+        .unwrap();
+        assert!(fields.timestamp.is_some());
+        assert_eq!(fields.level, Some(Level::Error));
+        assert_eq!(fields.message.as_deref(), Some("failed"));
+        assert_eq!(
+            fields.extra,
+            vec![("req".to_string(), r#"{"id":7}"#.to_string())]
+        );
+
+        assert!(parse(r#"["not", "an", "object"]"#).is_none());
+        assert!(parse(r#"{"truncated": "#).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::format::Fields;
+
+/// Parse a line made up entirely of `key=value` pairs. Values with spaces in them are quoted,
+/// and a key on its own is a flag with an empty value. At least two pairs with values are needed,
+/// so that ordinary text with an `=` in it isn't taken for logfmt.
+pub fn parse(line: &str) -> Option<Fields> {
+    let mut pairs = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+        let key = take_while(&mut chars, |c| c != '=' && !c.is_whitespace());
+        if key.is_empty() || key.contains('"') {
+            return None;
+        }
+
+        let value = if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                quoted(&mut chars)?
+            } else {
+                let value = take_while(&mut chars, |c| !c.is_whitespace());
+                if value.contains('"') {
+                    return None;
+                }
+                value
+            }
+        } else {
+            String::new()
+        };
+        pairs.push((key, value));
+
+        // Pairs are separated by whitespace.
+        match chars.peek() {
+            Some(c) if c.is_whitespace() => {
+                take_while(&mut chars, char::is_whitespace);
+            }
+            Some(_) => return None,
+            None => {}
+        }
+    }
+
+    let with_values = pairs.iter().filter(|(_, value)| !value.is_empty()).count();
+    (with_values >= 2).then(|| Fields::from_pairs(pairs))
+}
+
+fn take_while(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    predicate: impl Fn(char) -> bool,
+) -> String {
+    let mut taken = String::new();
+    while let Some(c) = chars.next_if(|&c| predicate(c)) {
+        taken.push(c);
+    }
+    taken
+}
+
+/// The rest of a quoted value, after the opening quote. Returns `None` if it isn't closed.
+fn quoted(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Level;
+
+    #[test]
+    fn test_parse() {
+        let fields = parse(r#"level=info msg="user \"bob\" logged in" dur=12ms cached"#).unwrap();
+        assert_eq!(fields.level, Some(Level::Info));
+        assert_eq!(fields.message.as_deref(), Some(r#"user "bob" logged in"#));
+        assert_eq!(
+            fields.extra,
+            vec![
+                ("dur".to_string(), "12ms".to_string()),
+                ("cached".to_string(), String::new())
+            ]
+        );
+
+        assert!(parse("retrying with timeout=30").is_none());
+        assert!(parse(r#"a=1 b="unclosed"#).is_none());
+    }
+}
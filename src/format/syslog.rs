@@ -0,0 +1,78 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{
+    format::{Fields, Level},
+    timestamp::find_timestamp,
+};
+
+/// BSD syslog as written by syslogd and rsyslog, with either the traditional or a high-precision
+/// timestamp, and an optional `<priority>` if the line came straight off the wire.
+static SYSLOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        ^(?:<(?P<priority>\d{1,3})>)?
+        (?P<timestamp>[A-Z][a-z]{2}\ [\ \d]\d\ \d{2}:\d{2}:\d{2} | \d{4}-\d{2}-\d{2}T\S+)
+        \ (?P<host>\S+)
+        \ (?P<tag>[^:\[\s]+)(?:\[(?P<pid>\d+)\])?:
+        \ ?(?P<message>.*)$",
+    )
+    .unwrap()
+});
+
+pub fn parse(line: &str) -> Option<Fields> {
+    let captures = SYSLOG.captures(line)?;
+
+    let mut extra = vec![("host".to_string(), captures["host"].to_string())];
+    if let Some(pid) = captures.name("pid") {
+        extra.push(("pid".to_string(), pid.as_str().to_string()));
+    }
+    Some(Fields {
+        timestamp: find_timestamp(&captures["timestamp"]),
+        level: captures
+            .name("priority")
+            .and_then(|priority| priority.as_str().parse().ok())
+            .map(severity_level),
+        component: Some(captures["tag"].to_string()),
+        message: Some(captures["message"].to_string()),
+        extra,
+    })
+}
+
+/// The level for the severity encoded in the low bits of a syslog priority.
+fn severity_level(priority: u8) -> Level {
+    match priority % 8 {
+        0..=2 => Level::Fatal,
+        3 => Level::Error,
+        4 => Level::Warn,
+        5 | 6 => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let fields =
+            parse("<35>Jan  5 10:23:45 web1 sshd[4242]: Failed password for root").unwrap();
+        assert!(fields.timestamp.is_some());
+        assert_eq!(fields.level, Some(Level::Error));
+        assert_eq!(fields.component.as_deref(), Some("sshd"));
+        assert_eq!(fields.message.as_deref(), Some("Failed password for root"));
+        assert_eq!(
+            fields.extra,
+            vec![
+                ("host".to_string(), "web1".to_string()),
+                ("pid".to_string(), "4242".to_string())
+            ]
+        );
+
+        let fields = parse("2024-01-15T10:23:45.123+00:00 web1 kernel: oops").unwrap();
+        assert_eq!(fields.level, None);
+        assert_eq!(fields.component.as_deref(), Some("kernel"));
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    fs::{File, Metadata},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+use crate::{encoding::Encoding, state::LogLine};
+
+mod decompress;
+mod line_reader;
+mod merge;
+
+pub use decompress::Compression;
+pub use line_reader::LineReader;
+pub use merge::merge_inputs;
+
+/// How long to wait at the end of a followed file before checking it for new data.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub enum InputSource {
+    File(PathBuf),
+    /// A file that keeps being read as it grows, surviving truncation and rotation.
+    Follow(PathBuf),
+    Stdin,
+}
+
+impl InputSource {
+    pub fn name(&self) -> String {
+        match self {
+            InputSource::File(path) | InputSource::Follow(path) => path.display().to_string(),
+            InputSource::Stdin => "stdin".to_string(),
+        }
+    }
+}
+
+/// Something that happened while reading an input source.
+pub enum InputEvent {
+    Line(LogLine),
+    /// Reading failed, and the source won't produce any more lines.
+    Error {
+        source: usize,
+        message: String,
+    },
+}
+
+/// Sends lines read from one input source to the UI thread, tagged with the source's index.
+#[derive(Clone)]
+pub struct LineSender {
+    source: usize,
+    tx: Sender<InputEvent>,
+}
+
+impl LineSender {
+    pub fn new(source: usize, tx: Sender<InputEvent>) -> Self {
+        Self { source, tx }
+    }
+
+    fn send(&self, text: String) {
+        let _ = self
+            .tx
+            .send(InputEvent::Line(LogLine::new(self.source, text)));
+    }
+
+    fn error(&self, error: io::Error) {
+        let _ = self.tx.send(InputEvent::Error {
+            source: self.source,
+            message: error.to_string(),
+        });
+    }
+}
+
+/// Read lines from `input` until it ends, decoding them with `encoding` (or the encoding
+/// indicated by a byte order mark if `None`). Errors are reported on `tx` rather than returned.
+pub fn read_from_input(input: InputSource, encoding: Option<Encoding>, tx: LineSender) {
+    let result = match input {
+        InputSource::File(path) => {
+            decompress::open(&path).and_then(|reader| send_all(reader, encoding, &tx))
+        }
+        // A compressed file is an archived log that won't grow, and a pipe can't be truncated or
+        // rotated, so there's nothing to follow in either.
+        InputSource::Follow(path) if !is_regular_file(&path) || is_compressed(&path) => {
+            decompress::open(&path).and_then(|reader| send_all(reader, encoding, &tx))
+        }
+        InputSource::Follow(path) => follow_file(path, encoding, &tx),
+        InputSource::Stdin => send_all(BufReader::new(std::io::stdin()), encoding, &tx),
+    };
+
+    if let Err(error) = result {
+        tx.error(error);
+    }
+}
+
+/// Whether the file at `path` is compressed. Only regular files are checked, since reading the
+/// start of a pipe would take those bytes away from the reader that opens it next.
+pub fn is_compressed(path: &Path) -> bool {
+    is_regular_file(path) && matches!(Compression::detect(path), Ok(Some(_)))
+}
+
+fn is_regular_file(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+}
+
+/// Whether the file at `path` can be memory-mapped and split into lines by looking for `\n`
+/// bytes, rather than having to be streamed through a decoder. Only regular files can be mapped,
+/// so pipes like `cant <(cmd)` are streamed.
+pub fn is_mappable(path: &Path, encoding: Option<Encoding>) -> bool {
+    if !is_regular_file(path) {
+        return false;
+    }
+
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => {
+            let mut header = Vec::new();
+            let Ok(file) = File::open(path) else {
+                return false;
+            };
+            if file.take(4).read_to_end(&mut header).is_err() {
+                return false;
+            }
+            Encoding::detect_bom(&header).map_or(Encoding::Utf8, |(encoding, _)| encoding)
+        }
+    };
+
+    encoding.is_ascii_compatible() && !is_compressed(path)
+}
+
+fn send_all(reader: impl BufRead, encoding: Option<Encoding>, tx: &LineSender) -> io::Result<()> {
+    for line in LineReader::new(reader, encoding) {
+        tx.send(line?);
+    }
+    Ok(())
+}
+
+/// Read `path` to the end and then keep watching it for new lines, like `tail -F`.
+///
+/// If the file shrinks it is assumed to have been truncated in place (e.g. by logrotate's
+/// `copytruncate`) and is read again from the start. If the path starts referring to a different
+/// file, the old one is read to the end and the new one is opened. In both cases a marker line is
+/// sent so the rotation point is visible in the log.
+pub fn follow_file(path: PathBuf, encoding: Option<Encoding>, tx: &LineSender) -> io::Result<()> {
+    let file = File::open(&path)?;
+    let mut identity = file_identity(&file.metadata()?);
+    let mut lines = LineReader::new(BufReader::new(file), encoding);
+
+    loop {
+        send_available(&mut lines, tx)?;
+        std::thread::sleep(FOLLOW_POLL_INTERVAL);
+
+        // The file may briefly not exist between being moved away and being recreated.
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+
+        if file_identity(&metadata) != identity {
+            let Ok(file) = File::open(&path) else {
+                continue;
+            };
+
+            // Pick up anything written to the old file before it was replaced.
+            send_available(&mut lines, tx)?;
+            if let Some(line) = lines.finish() {
+                tx.send(line);
+            }
+
+            identity = file_identity(&file.metadata()?);
+            lines.reset(BufReader::new(file));
+            send_marker(&path, "was rotated; following the new file", tx);
+        } else if metadata.len() < lines.position()? {
+            lines.rewind()?;
+            send_marker(&path, "was truncated; reading from the start", tx);
+        }
+    }
+}
+
+/// Send every complete line that is available. A trailing partial line is held back until the
+/// rest of it is written.
+fn send_available<R: BufRead>(lines: &mut LineReader<R>, tx: &LineSender) -> io::Result<()> {
+    while let Some(line) = lines.read_line()? {
+        tx.send(line);
+    }
+    Ok(())
+}
+
+fn send_marker(path: &Path, message: &str, tx: &LineSender) {
+    tx.send(format!("--- {} {} ---", path.display(), message));
+}
+
+#[cfg(unix)]
+fn file_identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Without inode numbers we can't tell a recreated file apart, so only truncation is detected.
+#[cfg(not(unix))]
+fn file_identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::OpenOptions, io::Write, sync::mpsc::Receiver};
+
+    use super::*;
+
+    fn recv(rx: &Receiver<InputEvent>) -> String {
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            InputEvent::Line(line) => line.text,
+            InputEvent::Error { message, .. } => panic!("{}", message),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_truncation_and_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "one\ntwo\npart").unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn({
+            let path = path.clone();
+            move || follow_file(path, None, &LineSender::new(0, tx))
+        });
+        assert_eq!(recv(&rx), "one");
+        assert_eq!(recv(&rx), "two");
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"ial\n").unwrap();
+        assert_eq!(recv(&rx), "partial");
+
+        file.set_len(0).unwrap();
+        assert!(recv(&rx).contains("was truncated"));
+        std::fs::write(&path, "after truncate\n").unwrap();
+        assert_eq!(recv(&rx), "after truncate");
+
+        std::fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        file.write_all(b"last words\n").unwrap();
+        std::fs::write(&path, "new file\n").unwrap();
+        assert_eq!(recv(&rx), "last words");
+        assert!(recv(&rx).contains("was rotated"));
+        assert_eq!(recv(&rx), "new file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fifo_is_streamed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pipe");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(!is_mappable(&path, None));
+        assert!(!is_compressed(&path));
+
+        for input in [
+            InputSource::File(path.clone()),
+            InputSource::Follow(path.clone()),
+        ] {
+            std::thread::spawn({
+                let path = path.clone();
+                move || std::fs::write(path, "one\ntwo\n")
+            });
+            let (tx, rx) = std::sync::mpsc::channel();
+            read_from_input(input, None, LineSender::new(0, tx));
+            assert_eq!(recv(&rx), "one");
+            assert_eq!(recv(&rx), "two");
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// A compression format, recognized by the magic bytes at the start of a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    const MAGIC: [(Compression, &'static [u8]); 4] = [
+        (Compression::Gzip, &[0x1f, 0x8b]),
+        (Compression::Zstd, &[0x28, 0xb5, 0x2f, 0xfd]),
+        (Compression::Bzip2, b"BZh"),
+        (Compression::Xz, &[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+    ];
+
+    fn sniff(header: &[u8]) -> Option<Self> {
+        Self::MAGIC
+            .iter()
+            .find(|(_, magic)| header.starts_with(magic))
+            .map(|(compression, _)| *compression)
+    }
+
+    /// Detect the compression format of the file at `path`, if any.
+    pub fn detect(path: &Path) -> io::Result<Option<Self>> {
+        let mut header = Vec::with_capacity(6);
+        File::open(path)?.take(6).read_to_end(&mut header)?;
+        Ok(Self::sniff(&header))
+    }
+
+    fn feature(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+        }
+    }
+}
+
+/// Open the file at `path` for reading, transparently decompressing it if it is compressed in a
+/// format this build supports.
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let Some(compression) = Compression::sniff(reader.fill_buf()?) else {
+        return Ok(Box::new(reader));
+    };
+
+    Ok(Box::new(BufReader::new(decoder(
+        path,
+        compression,
+        reader,
+    )?)))
+}
+
+fn decoder(
+    path: &Path,
+    compression: Compression,
+    reader: BufReader<File>,
+) -> io::Result<Box<dyn Read + Send>> {
+    match compression {
+        // Concatenated members (e.g. from `cat a.gz b.gz`) are valid gzip and must all be read.
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(zstd::Decoder::with_buffer(reader)?)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader))),
+        #[allow(unreachable_patterns)]
+        compression => {
+            drop(reader);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{} is {} compressed, but cant was built without the `{}` feature",
+                    path.display(),
+                    compression.feature(),
+                    compression.feature()
+                ),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(
+            Compression::sniff(&[0x1f, 0x8b, 0x08]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::sniff(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::sniff(b"BZh91AY"), Some(Compression::Bzip2));
+        assert_eq!(
+            Compression::sniff(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+            Some(Compression::Xz)
+        );
+        assert_eq!(Compression::sniff(b"2024-01-15 plain"), None);
+        assert_eq!(Compression::sniff(b""), None);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_open_multi_member_gzip() {
+        use std::io::Write;
+
+        let gzip = |text: &str| {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log.gz");
+        std::fs::write(&path, [gzip("one\ntwo\n"), gzip("three\n")].concat()).unwrap();
+
+        let lines = open(&path)
+            .unwrap()
+            .lines()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, vec!["one", "two", "three"]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::{self, BufRead, Seek};
+
+use crate::encoding::Encoding;
+
+/// Splits a byte stream into decoded lines. Unlike `BufRead::lines`, invalid input never fails a
+/// read, and a partial line at the end of the stream is held back until the rest of it arrives,
+/// which is what following a growing file needs.
+pub struct LineReader<R> {
+    reader: R,
+    /// The encoding to decode with, or `None` to detect it from a byte order mark.
+    encoding: Option<Encoding>,
+    /// The encoding detected from the start of the current stream.
+    detected: Option<Encoding>,
+    pending: Vec<u8>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R, encoding: Option<Encoding>) -> Self {
+        Self {
+            reader,
+            encoding,
+            detected: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Read the next complete line. Returns `None` when no complete line is available yet.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let encoding = self.encoding()?;
+        loop {
+            if let Some(end) = self.line_end(encoding) {
+                let rest = self.pending.split_off(end);
+                let line = std::mem::replace(&mut self.pending, rest);
+                return Ok(Some(encoding.decode_line(&line)));
+            }
+
+            let read = if encoding.is_ascii_compatible() {
+                self.reader.read_until(b'\n', &mut self.pending)?
+            } else {
+                self.read_until_newline(encoding.newline())?
+            };
+            if read == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Like `read_until`, but for a newline that is a whole code unit of a wider encoding. A
+    /// `\n` byte alone may be only half of one, so stopping there would leave the rest of the
+    /// newline unread until more data arrives.
+    fn read_until_newline(&mut self, newline: &[u8]) -> io::Result<usize> {
+        let mut read = 0;
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(read);
+            }
+
+            // Code units are counted from the start of `pending`, which is the start of a line.
+            let skip = (newline.len() - self.pending.len() % newline.len()) % newline.len();
+            let end = available
+                .get(skip..)
+                .and_then(|rest| {
+                    rest.chunks_exact(newline.len())
+                        .position(|unit| unit == newline)
+                })
+                .map(|i| skip + (i + 1) * newline.len());
+
+            let length = end.unwrap_or(available.len());
+            self.pending.extend_from_slice(&available[..length]);
+            self.reader.consume(length);
+            read += length;
+            if end.is_some() {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Take whatever partial line is left once the stream has really ended.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.pending);
+        let encoding = self.encoding.or(self.detected).unwrap_or(Encoding::Utf8);
+        Some(encoding.decode_line(&line))
+    }
+
+    /// Start reading from a new stream, e.g. after the file being followed was replaced. Any
+    /// partial line from the old stream is discarded.
+    pub fn reset(&mut self, reader: R) {
+        self.reader = reader;
+        self.detected = None;
+        self.pending.clear();
+    }
+
+    fn encoding(&mut self) -> io::Result<Encoding> {
+        if let Some(encoding) = self.encoding.or(self.detected) {
+            return Ok(encoding);
+        }
+
+        let header = self.reader.fill_buf()?;
+        if header.is_empty() {
+            // Nothing has been written yet, so there's nothing to detect from.
+            return Ok(Encoding::Utf8);
+        }
+
+        let (encoding, bom_length) = Encoding::detect_bom(header).unwrap_or((Encoding::Utf8, 0));
+        self.reader.consume(bom_length);
+        self.detected = Some(encoding);
+        Ok(encoding)
+    }
+
+    /// The length of the first complete line in `pending`, including its line ending.
+    fn line_end(&self, encoding: Encoding) -> Option<usize> {
+        let newline = encoding.newline();
+        if encoding.is_ascii_compatible() {
+            memchr::memchr(b'\n', &self.pending).map(|i| i + 1)
+        } else {
+            self.pending
+                .chunks_exact(newline.len())
+                .position(|unit| unit == newline)
+                .map(|i| (i + 1) * newline.len())
+        }
+    }
+}
+
+impl<R: BufRead + Seek> LineReader<R> {
+    /// How far into the stream has been read, including any partial line being held back.
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.reader.stream_position()
+    }
+
+    /// Go back to the start of the stream, e.g. after the file being followed was truncated.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.reader.rewind()?;
+        self.detected = None;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<String>;
+
+    /// Iterate over every line until the end of the stream, including a final partial line.
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_line() {
+            Ok(Some(line)) => Some(Ok(line)),
+            Ok(None) => self.finish().map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_with_bom() {
+        let bytes = "\u{feff}one\r\ntwo\nthree"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+
+        let lines = LineReader::new(&bytes[..], None)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_utf16_lines_are_not_held_back() {
+        /// A stream with nothing more to read yet.
+        struct Waiting;
+        impl io::Read for Waiting {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+        }
+
+        let bytes = "one\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let stream = io::BufReader::new(io::Read::chain(&bytes[..], Waiting));
+        let mut reader = LineReader::new(stream, Some(Encoding::Utf16le));
+        assert_eq!(reader.read_line().unwrap(), Some("one".to_string()));
+    }
+
+    #[test]
+    fn test_partial_lines_are_held_back() {
+        let mut reader = LineReader::new(&b"one\ntw"[..], Some(Encoding::Utf8));
+        assert_eq!(reader.read_line().unwrap(), Some("one".to_string()));
+        assert_eq!(reader.read_line().unwrap(), None);
+        assert_eq!(reader.finish(), Some("tw".to_string()));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    encoding::Encoding,
+    input::{read_from_input, InputEvent, InputSource, LineSender},
+    state::LogLine,
+    timestamp::find_timestamp,
+};
+
+/// How long to wait when no input has anything new before checking again.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long to hold back the other inputs while waiting on one that has gone quiet. Without this
+/// a followed file that stops being written to would stall the whole merge.
+const STALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A line with a timestamp, followed by any lines without one (stack traces, wrapped messages)
+/// that belong with it.
+struct Entry {
+    timestamp: Option<NaiveDateTime>,
+    lines: Vec<LogLine>,
+}
+
+struct MergeInput {
+    rx: Receiver<InputEvent>,
+    /// Entries that are complete and waiting to be merged, in the order they were read.
+    ready: VecDeque<Entry>,
+    /// The entry currently being read, which may still gain continuation lines.
+    pending: Option<Entry>,
+    /// The timestamp of the last line that had one, which continuation lines that arrive after
+    /// their entry was flushed are sorted by.
+    last_timestamp: Option<NaiveDateTime>,
+    last_received: Instant,
+    finished: bool,
+}
+
+impl MergeInput {
+    fn new(rx: Receiver<InputEvent>) -> Self {
+        Self {
+            rx,
+            ready: VecDeque::new(),
+            pending: None,
+            last_timestamp: None,
+            last_received: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// Take everything the input has sent so far. Errors aren't part of the merge, so they are
+    /// passed straight on to `tx`.
+    fn receive(&mut self, tx: &Sender<InputEvent>) -> bool {
+        let mut received = false;
+        loop {
+            match self.rx.try_recv() {
+                Ok(InputEvent::Line(line)) => {
+                    self.push_line(line);
+                    received = true;
+                }
+                Ok(error @ InputEvent::Error { .. }) => {
+                    let _ = tx.send(error);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+
+        if received {
+            self.last_received = Instant::now();
+        }
+        // Nothing more can be added to the pending entry if the input has ended or gone quiet.
+        if self.finished || self.last_received.elapsed() > STALL_TIMEOUT {
+            self.ready.extend(self.pending.take());
+        }
+
+        received
+    }
+
+    fn push_line(&mut self, line: LogLine) {
+        match (find_timestamp(&line.text), self.pending.as_mut()) {
+            (None, Some(entry)) => entry.lines.push(line),
+            (timestamp, _) => {
+                // A continuation line whose entry went out while the input was quiet still
+                // belongs at the time of that entry, not before everything else.
+                if timestamp.is_some() {
+                    self.last_timestamp = timestamp;
+                }
+                self.ready.extend(self.pending.take());
+                self.pending = Some(Entry {
+                    timestamp: self.last_timestamp,
+                    lines: vec![line],
+                });
+            }
+        }
+    }
+
+    /// Whether the merge has to wait on this input before it knows what comes next.
+    fn is_blocking(&self) -> bool {
+        self.ready.is_empty() && !self.finished
+    }
+
+    fn is_done(&self) -> bool {
+        self.finished && self.ready.is_empty() && self.pending.is_none()
+    }
+}
+
+/// Read every input in parallel and send their lines on `tx` in timestamp order, by repeatedly
+/// taking the earliest entry at the head of any input. Each input is assumed to already be in
+/// order; lines before the first timestamp in an input sort first, and other lines without one
+/// go with the line before them.
+pub fn merge_inputs(inputs: Vec<InputSource>, encoding: Option<Encoding>, tx: Sender<InputEvent>) {
+    let mut inputs = inputs
+        .into_iter()
+        .enumerate()
+        .map(|(source, input)| {
+            let (input_tx, rx) = mpsc::channel();
+            let input_tx = LineSender::new(source, input_tx);
+            std::thread::spawn(move || read_from_input(input, encoding, input_tx));
+            MergeInput::new(rx)
+        })
+        .collect::<Vec<_>>();
+
+    let mut stalled_since = None;
+    loop {
+        let mut progressed = false;
+        for input in inputs.iter_mut() {
+            progressed |= input.receive(&tx);
+        }
+
+        if inputs.iter().all(MergeInput::is_done) {
+            return;
+        }
+
+        let can_merge = if inputs.iter().any(MergeInput::is_blocking) {
+            stalled_since.get_or_insert_with(Instant::now).elapsed() > STALL_TIMEOUT
+        } else {
+            stalled_since = None;
+            true
+        };
+
+        if can_merge {
+            let earliest = inputs
+                .iter_mut()
+                .filter(|input| !input.ready.is_empty())
+                .min_by_key(|input| input.ready[0].timestamp);
+            if let Some(input) = earliest {
+                for line in input.ready.pop_front().unwrap().lines {
+                    if tx.send(InputEvent::Line(line)).is_err() {
+                        return;
+                    }
+                }
+                progressed = true;
+            }
+        }
+
+        if !progressed {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_by_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let api = dir.path().join("api.log");
+        let worker = dir.path().join("worker.log");
+        std::fs::write(
+            &api,
+            "2024-01-15 10:00:00 api start\n\
+             2024-01-15 10:00:02 api error\n\
+             \tat handler.rs:12\n\
+             2024-01-15 10:00:04 api done\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &worker,
+            "2024-01-15 10:00:01 worker start\n\
+             2024-01-15 10:00:03 worker busy\n",
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        merge_inputs(
+            vec![InputSource::File(api), InputSource::File(worker)],
+            None,
+            tx,
+        );
+        let lines = rx
+            .iter()
+            .map(|event| match event {
+                InputEvent::Line(line) => (line.source, line.text),
+                InputEvent::Error { message, .. } => panic!("{}", message),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                (0, "2024-01-15 10:00:00 api start".to_string()),
+                (1, "2024-01-15 10:00:01 worker start".to_string()),
+                (0, "2024-01-15 10:00:02 api error".to_string()),
+                (0, "\tat handler.rs:12".to_string()),
+                (1, "2024-01-15 10:00:03 worker busy".to_string()),
+                (0, "2024-01-15 10:00:04 api done".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_continuation_after_stall() {
+        let (input_tx, rx) = mpsc::channel();
+        let (tx, _rx) = mpsc::channel();
+        let mut input = MergeInput::new(rx);
+        let send = |text: &str| {
+            input_tx
+                .send(InputEvent::Line(LogLine::new(0, text)))
+                .unwrap()
+        };
+
+        send("2024-01-15 10:00:02 api error");
+        input.receive(&tx);
+        assert!(input.ready.is_empty());
+
+        // The rest of the stack trace only arrives after the input has gone quiet for a while.
+        input.last_received -= STALL_TIMEOUT * 2;
+        input.receive(&tx);
+        assert_eq!(input.ready.len(), 1);
+        send("\tat handler.rs:12");
+        input.receive(&tx);
+
+        let entry = input.pending.as_ref().unwrap();
+        assert_eq!(entry.lines[0].text, "\tat handler.rs:12");
+        assert_eq!(entry.timestamp, input.ready[0].timestamp);
+        assert!(entry.timestamp.is_some());
+    }
+}
@@ -1,51 +1,205 @@
-use std::{
-    error::Error,
-    io::{BufRead, BufReader},
-    path::PathBuf,
-    rc::Rc,
-    sync::RwLock,
-};
+use std::{error::Error, path::PathBuf, rc::Rc, sync::RwLock};
 
-use crate::state::AppState;
-use clap::{command, Parser};
-use ratatui::DefaultTerminal;
+use crate::{
+    encoding::Encoding,
+    format::{default_formats_path, load_formats, Level, LogFormat},
+    input::{is_mappable, merge_inputs, read_from_input, InputEvent, InputSource, LineSender},
+    state::{AppState, History, LogBuffer},
+    timestamp::parse_time_from,
+    ui::level_colors::parse_level_color,
+};
+use chrono::{Local, NaiveDateTime};
+use clap::Parser;
+use ratatui::{style::Color, DefaultTerminal};
 
+mod clipboard;
+mod encoding;
 mod events;
+mod format;
+mod input;
 mod state;
+mod timestamp;
 mod ui;
 
-enum InputSource {
-    File(PathBuf),
-    Stdin,
-}
-
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    input: Option<String>,
+    /// Files to read, or `-` for stdin. Reads stdin if none are given
+    inputs: Vec<String>,
+
+    /// Keep reading the file as it grows, following truncation and rotation like `tail -F`
+    #[arg(short, long)]
+    follow: bool,
+
+    /// Interleave the inputs by the timestamp on each line instead of the order lines arrive in
+    #[arg(short, long)]
+    merge: bool,
+
+    /// Encoding of the inputs. Detected from a byte order mark if not given, otherwise UTF-8
+    #[arg(short, long, value_enum)]
+    encoding: Option<Encoding>,
+
+    /// Parse every input in this format instead of detecting it. Either a built-in format (json,
+    /// logfmt, syslog, access or bracketed) or one from `~/.config/cant/formats.toml`
+    #[arg(long, value_name = "NAME")]
+    format: Option<String>,
+
+    /// Only show lines logged at or after this time, like `2026-10-17 14:00`, `14:00` (today),
+    /// `-15m` or `1h ago`. Lines without a timestamp go with the line before them
+    #[arg(long, value_name = "TIME", allow_hyphen_values = true, value_parser = parse_cli_time)]
+    since: Option<NaiveDateTime>,
+
+    /// Only show lines logged before this time, in the same forms as `--since`
+    #[arg(long, value_name = "TIME", allow_hyphen_values = true, value_parser = parse_cli_time)]
+    until: Option<NaiveDateTime>,
+
+    /// Number of columns between tab stops
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    tab_width: u16,
+
+    /// Color lines of a level differently, like `error=red`, `warn=#ffaa00` or `debug=none`.
+    /// Can be given more than once
+    #[arg(long, value_name = "LEVEL=COLOR", value_parser = parse_level_color)]
+    level_color: Vec<(Level, Option<Color>)>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
+    // Asking for a format that can't be used is reported before the terminal is taken over.
+    let formats = match load_log_formats(cli.format.as_deref()) {
+        Ok(formats) => formats,
+        Err(message) => {
+            eprintln!("error: {}", message.trim_end());
+            std::process::exit(2);
+        }
+    };
+    if let (Some(since), Some(until)) = (cli.since, cli.until) {
+        if until <= since {
+            eprintln!("error: --until has to be after --since");
+            std::process::exit(2);
+        }
+    }
+    let problems = formats.problems.clone();
     let terminal = ratatui::init();
-    run(terminal, cli)?;
+    let result = run(terminal, cli, formats);
     ratatui::restore();
-    Ok(())
+    // Formats that were skipped are only mentioned in the app, so list them in full on the way out.
+    for problem in &problems {
+        eprintln!("warning: {}", problem);
+    }
+    result
 }
 
-fn run(mut terminal: DefaultTerminal, cli: Cli) -> Result<(), Box<dyn Error>> {
-    let input = cli
-        .input
+/// The formats inputs could be in.
+struct Formats {
+    /// The built-in formats and the user's own from the formats file.
+    candidates: Vec<LogFormat>,
+    /// The format every input is parsed in, if one was asked for by name.
+    format: Option<LogFormat>,
+    /// What was wrong with the formats in the formats file that were skipped.
+    problems: Vec<String>,
+}
+
+/// Parse the time given to `--since` or `--until`. Relative times count back from now.
+fn parse_cli_time(text: &str) -> Result<NaiveDateTime, String> {
+    parse_time_from(text, Local::now().naive_local())
+        .ok_or_else(|| "expected a time like 2026-10-17 14:00, 14:00 or -15m".to_string())
+}
+
+/// Load the formats from the formats file, and find the one asked for by `name`, if any.
+/// Formats in the file that are invalid are skipped, unless one of them may have been the one
+/// asked for, which is an error.
+fn load_log_formats(name: Option<&str>) -> Result<Formats, String> {
+    let (custom, problems) = match default_formats_path() {
+        Some(path) => load_formats(&path),
+        None => (Vec::new(), Vec::new()),
+    };
+    let candidates = LogFormat::candidates(custom);
+
+    let format = match name {
+        None => None,
+        Some(name) => Some(find_format(&candidates, name, &problems)?),
+    };
+    Ok(Formats {
+        candidates,
+        format,
+        problems,
+    })
+}
+
+fn find_format(
+    candidates: &[LogFormat],
+    name: &str,
+    problems: &[String],
+) -> Result<LogFormat, String> {
+    match candidates.iter().find(|format| format.name() == name) {
+        Some(format) => Ok(format.clone()),
+        None => {
+            let names = candidates.iter().map(LogFormat::name).collect::<Vec<_>>();
+            let mut message = format!(
+                "unknown format `{}`, expected one of: {}",
+                name,
+                names.join(", ")
+            );
+            for problem in problems {
+                message.push_str(&format!("\nnote: skipped {}", problem));
+            }
+            Err(message)
+        }
+    }
+}
+
+fn run(mut terminal: DefaultTerminal, cli: Cli, formats: Formats) -> Result<(), Box<dyn Error>> {
+    let mut inputs = cli
+        .inputs
+        .iter()
         .map(|s| match s.as_str() {
             "-" => InputSource::Stdin,
+            s if cli.follow => InputSource::Follow(PathBuf::from(s)),
             s => InputSource::File(PathBuf::from(s)),
         })
-        .unwrap_or(InputSource::Stdin);
+        .collect::<Vec<_>>();
+    if inputs.is_empty() {
+        inputs.push(InputSource::Stdin);
+    }
+    let names = inputs.iter().map(InputSource::name).collect();
 
-    let state = Rc::new(RwLock::new(AppState::new()));
+    let encoding = cli.encoding;
 
     let (tx, rx) = std::sync::mpsc::channel();
-    std::thread::spawn(move || read_from_input(input, tx));
+    let mut state = match inputs.as_slice() {
+        _ if cli.merge => {
+            std::thread::spawn(move || merge_inputs(inputs, encoding, tx));
+            AppState::new(names)
+        }
+        // A single uncompressed file is mapped and indexed in the background rather than streamed
+        // through the channel.
+        [InputSource::File(path)] if is_mappable(path, encoding) => {
+            AppState::with_buffer(LogBuffer::open_mapped(path, encoding)?, names)
+        }
+        _ => {
+            for (source, input) in inputs.into_iter().enumerate() {
+                let tx = LineSender::new(source, tx.clone());
+                std::thread::spawn(move || read_from_input(input, encoding, tx));
+            }
+            AppState::new(names)
+        }
+    };
+    state.set_formats(formats.candidates, formats.format);
+    state.warnings = formats.problems;
+    state.tab_width = cli.tab_width as usize;
+    for &(level, color) in &cli.level_color {
+        state.level_colors.set(level, color);
+    }
+    if cli.since.is_some() || cli.until.is_some() {
+        let since = cli.since.unwrap_or(NaiveDateTime::MIN);
+        let until = cli.until.unwrap_or(NaiveDateTime::MAX);
+        state.set_time_range(Some(since..until));
+    }
+    if let Some(path) = History::default_path() {
+        state.history = History::load(path);
+    }
+    let state = Rc::new(RwLock::new(state));
 
     loop {
         if state.read().unwrap().quit {
@@ -54,9 +208,15 @@ fn run(mut terminal: DefaultTerminal, cli: Cli) -> Result<(), Box<dyn Error>> {
 
         {
             let mut state = state.write().unwrap();
-            while let Ok(line) = rx.try_recv() {
-                state.add_line(line);
+            while let Ok(event) = rx.try_recv() {
+                match event {
+                    InputEvent::Line(line) => state.add_line(line),
+                    InputEvent::Error { source, message } => {
+                        state.set_source_error(source, message)
+                    }
+                }
             }
+            state.sync_lines();
         }
 
         terminal.draw(|f| {
@@ -69,21 +229,3 @@ fn run(mut terminal: DefaultTerminal, cli: Cli) -> Result<(), Box<dyn Error>> {
         }
     }
 }
-
-fn read_from_input(input: InputSource, tx: std::sync::mpsc::Sender<String>) {
-    match input {
-        InputSource::File(path) => {
-            let file = std::fs::File::open(path).unwrap();
-            let reader = BufReader::new(file);
-            for line in reader.lines() {
-                let _ = tx.send(line.unwrap());
-            }
-        }
-        InputSource::Stdin => {
-            let reader = BufReader::new(std::io::stdin());
-            for line in reader.lines() {
-                let _ = tx.send(line.unwrap());
-            }
-        }
-    }
-}
@@ -1,45 +1,218 @@
+mod columns;
+mod detail;
+mod facets;
+mod filter;
+mod histogram;
+mod history;
 mod line_buffer;
+mod line_target;
+mod pattern;
+mod query;
+mod search;
+mod view;
 
+pub use columns::Columns;
+pub use detail::{find_json, Detail, Node, NodeKey};
+pub use facets::{FacetRow, Facets};
+pub use filter::{FieldFilter, Filter, FilterChain, FilterKind};
+pub use histogram::{Bucket, Histogram, SLOTS};
+pub use history::{History, HistoryKind};
+pub use line_buffer::{LogBuffer, LogLine};
+pub use line_target::LineTarget;
+pub use pattern::{Pattern, PatternOptions, PatternToggle};
+pub use query::{Query, QueryError};
+pub use search::Search;
+pub use view::View;
+
+use chrono::{Local, NaiveDateTime};
 use ratatui::style::Color;
+use serde_json::Value;
 
 use crate::{
-    state::line_buffer::LogBuffer,
+    clipboard,
+    format::{FormatDetector, Level, LogFormat},
+    timestamp::{parse_time_from, parse_time_range},
     ui::{
         focusable_input::{InputHandler, MessageTranslator},
-        widgets::text_input::{TextInputMsg, TextInputState},
+        level_colors::LevelColors,
+        widgets::{
+            bar_select::{BarSelectMsg, BarSelectState},
+            list_select::{ListSelectMsg, ListSelectState},
+            text_input::{TextInputMsg, TextInputState},
+        },
     },
 };
 
-use std::{rc::Rc, sync::Mutex};
+use std::{
+    collections::HashMap,
+    ops::Range,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Longest time spent counting the lines of a memory-mapped file for the facet sidebar and the
+/// histogram each time lines are synced, so a large file is counted over several frames.
+const COUNT_BUDGET: Duration = Duration::from_millis(20);
 
 type MessageTranslatorRc<T> = Rc<Mutex<MessageTranslator<T, AppAction>>>;
 type InputHandlerRc = Rc<Mutex<dyn InputHandler<Message = AppAction>>>;
 
 #[derive(Debug, Clone)]
 pub enum AppAction {
-    ScrollUp(u16),
-    ScrollDown(u16),
-    ScrollToLine(u16),
+    ScrollUp(u64),
+    ScrollDown(u64),
+    ScrollToLine(u64),
     AttachToBottom,
+    MoveCursor(isize),
+    OpenDetail,
+    CloseDetail,
+    ToggleDetailNode(usize),
+    CopyDetailPath(usize),
+    FilterDetailValue(usize, FilterKind),
     ToggleLineNumbers,
     OpenSearch,
     CloseSearch,
     AcceptSearch(String),
+    NextMatch,
+    PrevMatch,
+    CancelSearch,
+    OpenResults,
+    CloseResults,
+    SelectResult(usize),
+    AcceptResult(usize),
+    OpenFilter(FilterTarget),
+    CloseFilter,
+    AcceptFilter(FilterTarget, String),
+    OpenFilterPanel,
+    CloseFilterPanel,
+    ToggleFilter(usize),
+    InvertFilter(usize),
+    RemoveFilter(usize),
+    ClearFilters,
+    TogglePatternOption(PatternToggle),
+    PatternChanged,
+    CycleMinLevel,
+    OpenQuery,
+    CloseQuery,
+    AcceptQuery(String),
+    QueryChanged(String),
+    OpenFacetPanel,
+    CloseFacetPanel,
+    ToggleFacet(usize, FilterKind),
+    OpenHistogram,
+    CloseHistogram,
+    SelectBucket(usize),
+    AcceptBucket(usize),
+    RestrictToBucket(usize),
+    ClearTimeRange,
+    OpenGoToTime,
+    CloseGoToTime,
+    AcceptGoToTime(String),
+    OpenTimeRange,
+    CloseTimeRange,
+    AcceptTimeRange(String),
+    TimePromptChanged(String),
+    ToggleTableView,
+    OpenColumnPanel,
+    CloseColumnPanel,
+    ToggleColumn(usize),
+    MoveColumn(usize, isize),
+    ResizeColumn(usize, i16),
+    FillColumn(usize),
+    ToggleSource(usize),
     OpenGoToLine,
     CloseGoToLine,
-    AcceptGoToLine(u16),
-    SetGoToLineColor(Color),
+    AcceptGoToLine(String),
+    GoToLineChanged(String),
+    AwaitMark,
+    SetMark(char),
+    CancelMark,
     Quit,
 }
 
+/// The filter a filter prompt is for.
+#[derive(Debug, Clone, Copy)]
+pub enum FilterTarget {
+    New(FilterKind),
+    Existing(usize),
+}
+
+/// Where the view was, and the search that was active, when the search prompt was opened.
+struct SearchOrigin {
+    top: usize,
+    attached: bool,
+    search: Option<Search>,
+}
+
+/// An input that lines are read from.
+pub struct Source {
+    pub name: String,
+    pub visible: bool,
+    /// Why reading from the source stopped early, if it did.
+    pub error: Option<String>,
+    /// Works out the format of the source's lines so they can be parsed into fields.
+    pub format: FormatDetector,
+    /// Buffer indices of the lines sampled while the format isn't settled, which are parsed again
+    /// whenever the guess changes.
+    sampled: Vec<usize>,
+}
+
 pub struct AppState {
     pub lines: LogBuffer,
-    pub line_num: u16,
+    pub sources: Vec<Source>,
+    pub view: View,
+    /// Zero-based position of the first visible line in `view`.
+    pub line_num: u64,
     pub attached_to_bottom: bool,
+    /// Buffer index of the line the cursor is on, which the detail pane shows.
+    pub cursor: Option<usize>,
+    pub detail_pane: Option<MessageTranslatorRc<ListSelectState>>,
+    pub detail: Detail,
     pub search_input: Option<MessageTranslatorRc<TextInputState>>,
-    pub search: Option<String>,
+    pub search: Option<Search>,
+    search_origin: Option<SearchOrigin>,
+    /// The list of lines matching the search.
+    pub results_pane: Option<MessageTranslatorRc<ListSelectState>>,
+    /// Options for the search prompt, kept between searches.
+    pub search_options: PatternOptions,
+    pub filter_input: Option<MessageTranslatorRc<TextInputState>>,
+    pub filter_panel: Option<MessageTranslatorRc<ListSelectState>>,
+    pub filters: FilterChain,
+    /// Options for the filter prompt.
+    pub filter_options: PatternOptions,
+    pub query_input: Option<MessageTranslatorRc<TextInputState>>,
+    pub facets: Facets,
+    pub facet_panel: Option<MessageTranslatorRc<ListSelectState>>,
+    pub histogram: Histogram,
+    pub histogram_pane: Option<MessageTranslatorRc<BarSelectState>>,
+    /// How many bars fit in the histogram pane, as of the last frame.
+    pub histogram_width: u16,
+    /// The buffer index of the top line, and whether the view was attached to the bottom, from
+    /// before any filter was enabled. Restored once none are.
+    unfiltered_position: Option<(usize, bool)>,
     pub go_to_line: Option<MessageTranslatorRc<TextInputState>>,
+    /// Buffer index of the line the go-to-line prompt would go to as it is.
+    pub go_to_line_preview: Option<usize>,
+    /// Buffer indices of the lines marked with `m`, by the key they were marked with.
+    pub marks: HashMap<char, usize>,
+    /// Whether `m` was pressed, so the next key names a mark.
+    pub awaiting_mark: bool,
+    pub go_to_time: Option<MessageTranslatorRc<TextInputState>>,
+    pub time_range_input: Option<MessageTranslatorRc<TextInputState>>,
     pub show_line_numbers: bool,
+    /// Show the fields of parsed lines as columns instead of the raw text.
+    pub table_view: bool,
+    pub columns: Columns,
+    pub column_panel: Option<MessageTranslatorRc<ListSelectState>>,
+    /// What was entered into the prompts, in this session and earlier ones.
+    pub history: History,
+    /// Columns between tab stops when displaying lines.
+    pub tab_width: usize,
+    pub level_colors: LevelColors,
+    /// Problems with the configuration that didn't stop cant from starting, like formats that
+    /// were skipped.
+    pub warnings: Vec<String>,
 
     pub focused_input: Option<InputHandlerRc>,
     pub last_frame_height: u16,
@@ -47,15 +220,62 @@ pub struct AppState {
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    /// Create a state for lines read from the named sources, in order.
+    pub fn new(sources: Vec<String>) -> Self {
+        Self::with_buffer(LogBuffer::new(), sources)
+    }
+
+    pub fn with_buffer(lines: LogBuffer, sources: Vec<String>) -> Self {
+        let sources = sources
+            .into_iter()
+            .map(|name| Source {
+                name,
+                visible: true,
+                error: None,
+                format: FormatDetector::default(),
+                sampled: Vec::new(),
+            })
+            .collect();
+
         Self {
-            lines: LogBuffer::new(),
-            line_num: 1,
+            lines,
+            sources,
+            view: View::all(),
+            line_num: 0,
             attached_to_bottom: false,
+            cursor: None,
+            detail_pane: None,
+            detail: Detail::default(),
             search_input: None,
             search: None,
+            search_origin: None,
+            results_pane: None,
+            search_options: PatternOptions::default(),
+            filter_input: None,
+            filter_panel: None,
+            filters: FilterChain::default(),
+            filter_options: PatternOptions::default(),
+            query_input: None,
+            facets: Facets::default(),
+            facet_panel: None,
+            histogram: Histogram::default(),
+            histogram_pane: None,
+            histogram_width: 0,
+            unfiltered_position: None,
             go_to_line: None,
+            go_to_line_preview: None,
+            marks: HashMap::new(),
+            awaiting_mark: false,
+            go_to_time: None,
+            time_range_input: None,
             show_line_numbers: true,
+            table_view: false,
+            columns: Columns::default(),
+            column_panel: None,
+            history: History::default(),
+            tab_width: 8,
+            level_colors: LevelColors::default(),
+            warnings: Vec::new(),
 
             focused_input: None,
             last_frame_height: 0,
@@ -69,64 +289,448 @@ impl AppState {
             AppAction::ScrollDown(amount) => self.scroll_down_by(amount),
             AppAction::ScrollToLine(line_number) => self.set_line_number(line_number),
             AppAction::AttachToBottom => self.attach_to_bottom(),
+            AppAction::MoveCursor(offset) => self.move_cursor(offset),
+            AppAction::OpenDetail => self.open_detail(),
+            AppAction::CloseDetail => self.close_detail(),
+            AppAction::ToggleDetailNode(index) => {
+                if let Some(path) = self.detail_node(index, |node| node.path.clone()) {
+                    self.detail.toggle(&path);
+                }
+            }
+            AppAction::CopyDetailPath(index) => {
+                if let Some(path) = self.detail_node(index, |node| node.path.clone()) {
+                    if clipboard::copy(&path).is_ok() {
+                        self.detail.copied = Some(path);
+                    }
+                }
+            }
+            AppAction::FilterDetailValue(index, kind) => {
+                let value = self.detail_node(index, |node| {
+                    node.filter_value().map(|value| (node.path.clone(), value))
+                });
+                if let Some(Some((path, value))) = value {
+                    self.update_filters(|chain| chain.toggle_field_filter(kind, &path, &value));
+                }
+            }
             AppAction::ToggleLineNumbers => self.toggle_line_numbers(),
             AppAction::OpenSearch => self.open_search(),
-            AppAction::CloseSearch => self.close_search(),
+            AppAction::CloseSearch => self.cancel_search(),
             AppAction::OpenGoToLine => self.open_go_to_line(),
             AppAction::CloseGoToLine => self.close_go_to_line(),
             AppAction::Quit => self.quit = true,
             AppAction::AcceptSearch(query) => {
-                self.search = Some(query);
-                self.close_search();
+                // An invalid pattern leaves the prompt open so it can be fixed.
+                if self.accept_search(query).is_ok() {
+                    self.close_search();
+                }
+            }
+            AppAction::NextMatch => self.next_match(),
+            AppAction::PrevMatch => self.prev_match(),
+            AppAction::CancelSearch => self.cancel_background_search(),
+            AppAction::OpenResults => self.open_results(),
+            AppAction::CloseResults => self.close_results(),
+            AppAction::SelectResult(index) => self.select_result(index),
+            AppAction::AcceptResult(index) => {
+                self.select_result(index);
+                self.close_results();
+            }
+            AppAction::OpenFilter(target) => self.open_filter(target),
+            AppAction::CloseFilter => self.close_filter(),
+            AppAction::AcceptFilter(target, pattern) => {
+                if self.accept_filter(target, pattern).is_ok() {
+                    self.close_filter();
+                }
+            }
+            AppAction::OpenFilterPanel => self.open_filter_panel(),
+            AppAction::CloseFilterPanel => self.close_filter_panel(),
+            AppAction::ToggleFilter(index) => self.update_filters(|chain| {
+                if let Some(filter) = chain.filters.get_mut(index) {
+                    filter.enabled = !filter.enabled;
+                }
+            }),
+            AppAction::InvertFilter(index) => self.update_filters(|chain| {
+                if let Some(filter) = chain.filters.get_mut(index) {
+                    filter.kind = match filter.kind {
+                        FilterKind::Include => FilterKind::Exclude,
+                        FilterKind::Exclude => FilterKind::Include,
+                    };
+                }
+            }),
+            AppAction::RemoveFilter(index) => self.update_filters(|chain| {
+                if index < chain.filters.len() {
+                    chain.filters.remove(index);
+                }
+            }),
+            AppAction::ClearFilters => self.update_filters(|chain| chain.filters.clear()),
+            AppAction::TogglePatternOption(toggle) => self.toggle_pattern_option(toggle),
+            AppAction::PatternChanged => self.pattern_changed(),
+            AppAction::CycleMinLevel => self.update_filters(|chain| {
+                chain.min_level = Level::next_threshold(chain.min_level);
+            }),
+            AppAction::OpenQuery => self.open_query(),
+            AppAction::CloseQuery => self.close_query(),
+            AppAction::AcceptQuery(source) => {
+                if self.accept_query(&source).is_ok() {
+                    self.close_query();
+                }
+            }
+            AppAction::QueryChanged(source) => self.query_changed(&source),
+            AppAction::OpenFacetPanel => self.open_facet_panel(),
+            AppAction::CloseFacetPanel => self.close_facet_panel(),
+            AppAction::ToggleFacet(index, kind) => {
+                if let Some(FacetRow::Value { field, value, .. }) =
+                    self.facets.rows().get(index).copied()
+                {
+                    let (field, value) = (field.to_string(), value.to_string());
+                    self.update_filters(|chain| chain.toggle_field_filter(kind, &field, &value));
+                }
             }
-            AppAction::AcceptGoToLine(line_number) => {
-                self.set_line_number(line_number);
-                self.close_go_to_line();
+            AppAction::OpenHistogram => self.open_histogram(),
+            AppAction::CloseHistogram => self.close_histogram(),
+            AppAction::SelectBucket(index) => self.select_bucket(index),
+            AppAction::AcceptBucket(index) => {
+                self.select_bucket(index);
+                self.close_histogram();
             }
-            AppAction::SetGoToLineColor(color) => self.set_go_to_line_color(color),
+            AppAction::RestrictToBucket(index) => {
+                if let Some(bucket) = self.histogram.buckets.get(index) {
+                    let range = bucket.start..bucket.end;
+                    self.update_filters(|chain| {
+                        chain.time_range = match chain.time_range.take() {
+                            Some(current) if current == range => None,
+                            _ => Some(range),
+                        };
+                    });
+                }
+            }
+            AppAction::ClearTimeRange => self.set_time_range(None),
+            AppAction::OpenGoToTime => self.open_go_to_time(),
+            AppAction::CloseGoToTime => self.close_go_to_time(),
+            AppAction::AcceptGoToTime(input) => match self.accept_go_to_time(&input) {
+                Ok(()) => self.close_go_to_time(),
+                Err(message) => Self::set_prompt_error(self.go_to_time.as_ref(), message),
+            },
+            AppAction::OpenTimeRange => self.open_time_range(),
+            AppAction::CloseTimeRange => self.close_time_range(),
+            AppAction::AcceptTimeRange(input) => match self.accept_time_range(&input) {
+                Ok(()) => self.close_time_range(),
+                Err(message) => Self::set_prompt_error(self.time_range_input.as_ref(), message),
+            },
+            AppAction::TimePromptChanged(input) => self.time_prompt_changed(&input),
+            AppAction::ToggleTableView => self.table_view = !self.table_view,
+            AppAction::OpenColumnPanel => self.open_column_panel(),
+            AppAction::CloseColumnPanel => self.close_column_panel(),
+            AppAction::ToggleColumn(index) => self.columns.toggle(index),
+            AppAction::MoveColumn(index, offset) => {
+                let index = self.columns.move_by(index, offset);
+                // Keep the moved column selected.
+                if let Some(panel) = self.column_panel.as_ref() {
+                    panel.lock().unwrap().input_handler_mut().selected = index;
+                }
+            }
+            AppAction::ResizeColumn(index, delta) => self.columns.resize(index, delta),
+            AppAction::FillColumn(index) => self.columns.fill(index),
+            AppAction::ToggleSource(source) => self.toggle_source(source),
+            AppAction::AcceptGoToLine(input) => match self.accept_go_to_line(&input) {
+                Ok(()) => self.close_go_to_line(),
+                Err(message) => Self::set_prompt_error(self.go_to_line.as_ref(), message),
+            },
+            AppAction::GoToLineChanged(input) => self.go_to_line_changed(&input),
+            AppAction::AwaitMark => self.awaiting_mark = true,
+            AppAction::SetMark(mark) => {
+                self.awaiting_mark = false;
+                self.set_mark(mark);
+            }
+            AppAction::CancelMark => self.awaiting_mark = false,
+        }
+    }
+
+    /// Detect the format of each source from `candidates`, or parse every source as `format` if
+    /// one is given. Has to be called before any lines are added.
+    pub fn set_formats(&mut self, candidates: Vec<LogFormat>, format: Option<LogFormat>) {
+        let candidates: Arc<[LogFormat]> = candidates.into();
+        for source in &mut self.sources {
+            source.format = match format.clone() {
+                Some(format) => FormatDetector::fixed(format),
+                None => FormatDetector::new(candidates.clone()),
+            };
         }
+        self.lines.set_format(format);
     }
 
-    pub fn add_line(&mut self, line: String) {
+    pub fn add_line(&mut self, mut line: LogLine) {
+        let mut format_changed = false;
+        if let Some(source) = self.sources.get_mut(line.source) {
+            if !source.format.is_settled() {
+                let before = source.format.format().cloned();
+                source.format.sample(&line.text);
+                if source.format.format() != before.as_ref() {
+                    self.lines.reparse(&source.sampled, source.format.format());
+                    format_changed = true;
+                }
+                source.sampled.push(self.lines.len());
+                if source.format.is_settled() {
+                    source.sampled = Vec::new();
+                }
+            }
+            line.parse(source.format.format());
+        }
+
+        if format_changed {
+            self.recount();
+        }
+        self.facets.count(line.fields.as_deref());
+        self.histogram.count(&line);
         self.lines.add_line(line);
+        if format_changed {
+            self.rebuild_view();
+        }
     }
 
-    pub fn get_lines(&self) -> Vec<String> {
-        self.lines.get_lines(self.line_num, self.last_frame_height)
+    /// Count every line again for the facet sidebar and the histogram, after some were parsed
+    /// differently. Lines of a memory-mapped file are counted over the next few syncs instead.
+    fn recount(&mut self) {
+        self.facets = Facets::default();
+        self.histogram = Histogram::default();
+        if let LogBuffer::Memory(_) = self.lines {
+            for line in self.lines.iter_from(0) {
+                self.facets.count(line.fields.as_deref());
+                self.histogram.count(&line);
+            }
+        }
     }
 
-    pub fn scroll_up_by(&mut self, amount: u16) {
-        self.attached_to_bottom = false;
-        if amount >= self.line_num {
-            self.line_num = 1;
-        } else {
-            self.line_num -= amount;
+    pub fn set_source_error(&mut self, source: usize, message: String) {
+        if let Some(source) = self.sources.get_mut(source) {
+            source.error = Some(message);
         }
     }
 
-    pub fn scroll_down_by(&mut self, amount: u16) {
-        self.attached_to_bottom = false;
-        if self.line_num + amount > self.lines.len() as u16 - 1 {
-            self.line_num = self.lines.len() as u16 - 1;
-        } else {
-            self.line_num += amount;
+    /// Bring everything that tracks the buffer up to date with lines that have become
+    /// available since the last call, whether they were added or indexed in the background.
+    pub fn sync_lines(&mut self) {
+        if self.detect_mapped_format() {
+            // The lines already looked at were parsed in the old format.
+            self.recount();
+            self.rebuild_view();
+            return;
+        }
+        let sources = &self.sources;
+        let filters = &self.filters;
+        let tab_width = self.tab_width;
+        self.view.update(&self.lines, |line, context| {
+            sources[line.source].visible && filters.accepts(line, context, tab_width)
+        });
+
+        if let Some(search) = self.search.as_mut() {
+            search.update(&self.lines, &self.view);
+        }
+        self.follow_search_jump();
+
+        if self.attached_to_bottom {
+            self.attach_to_bottom();
+        }
+
+        if self.facet_panel.is_some() || self.histogram_pane.is_some() {
+            self.count_mapped_lines();
+        }
+        if self.histogram_pane.is_some() {
+            self.histogram.update(self.histogram_width as usize);
+        }
+
+        if self.table_view || self.column_panel.is_some() || self.query_input.is_some() {
+            self.discover_fields();
         }
     }
 
+    /// Offer columns, and completions in the query prompt, for the fields of the lines being
+    /// looked at.
+    fn discover_fields(&mut self) {
+        for (_, line) in self.get_lines() {
+            if let Some(fields) = line.fields.as_ref() {
+                self.columns.discover(fields);
+            }
+        }
+    }
+
+    /// Work out the format of a memory-mapped file from its first lines as they are indexed.
+    /// Lines read from memory are sampled as they are added instead. Returns whether the format
+    /// changed.
+    fn detect_mapped_format(&mut self) -> bool {
+        let (LogBuffer::Mapped(..), [source]) = (&self.lines, self.sources.as_mut_slice()) else {
+            return false;
+        };
+        if source.format.is_settled() || source.format.sampled() == self.lines.len() {
+            return false;
+        }
+
+        let before = source.format.format().cloned();
+        for text in self.lines.texts_from(source.format.sampled()) {
+            if source.format.is_settled() {
+                break;
+            }
+            source.format.sample(&text);
+        }
+        if source.format.format() == before.as_ref() {
+            return false;
+        }
+        self.lines.set_format(source.format.format().cloned());
+        true
+    }
+
+    /// Count the lines of a memory-mapped file for the facet sidebar and the histogram, for as
+    /// long as `COUNT_BUDGET` allows. Lines read from memory are counted as they are added
+    /// instead.
+    fn count_mapped_lines(&mut self) {
+        let LogBuffer::Mapped(_, format) = &self.lines else {
+            return;
+        };
+        // Lines that won't be parsed have no fields or timestamps, so they can all be counted at
+        // once.
+        if format.is_none() && self.sources.iter().all(|source| source.format.is_settled()) {
+            let uncounted = self.lines.len() - self.histogram.counted;
+            self.facets.counted += uncounted;
+            self.histogram.count_unparsed(uncounted);
+            return;
+        }
+        let start = Instant::now();
+        for (counted, line) in (0..).zip(self.lines.iter_from(self.histogram.counted)) {
+            // Checking the time is cheap, but not next to counting a line.
+            if counted % 1024 == 0 && start.elapsed() > COUNT_BUDGET {
+                break;
+            }
+            self.facets.count(line.fields.as_deref());
+            self.histogram.count(&line);
+        }
+    }
+
+    /// The lines in the viewport, along with their indices in the buffer.
+    pub fn get_lines(&self) -> Vec<(usize, LogLine)> {
+        self.view
+            .window(&self.lines, self.line_num as usize, self.last_frame_height)
+    }
+
+    pub fn scroll_up_by(&mut self, amount: u64) {
+        self.attached_to_bottom = false;
+        self.line_num = self.line_num.saturating_sub(amount);
+    }
+
+    pub fn scroll_down_by(&mut self, amount: u64) {
+        self.attached_to_bottom = false;
+        self.line_num = self
+            .line_num
+            .saturating_add(amount)
+            .min(self.last_line_index());
+    }
+
     pub fn attach_to_bottom(&mut self) {
         self.attached_to_bottom = true;
-        let last_line_number = self.lines.len() as u16;
-        if last_line_number > self.last_frame_height {
-            self.line_num = last_line_number - self.last_frame_height;
+        let line_count = self.view.len() as u64;
+        self.line_num = line_count.saturating_sub(self.last_frame_height as u64);
+    }
+
+    /// Scroll so that the one-based `line_number` is at the top of the view.
+    pub fn set_line_number(&mut self, line_number: u64) {
+        self.scroll_to_index(line_number.saturating_sub(1));
+    }
+
+    /// Scroll so that the buffer line at the zero-based `index`, or the first visible line after
+    /// it, is at the top of the view.
+    pub fn scroll_to_index(&mut self, index: u64) {
+        let position = self.view.position_of(index as usize) as u64;
+        self.line_num = position.min(self.last_line_index());
+    }
+
+    /// Buffer index of the line at the top of the view.
+    fn top_index(&self) -> usize {
+        self.view.buffer_index(self.line_num as usize).unwrap_or(0)
+    }
+
+    fn last_line_index(&self) -> u64 {
+        (self.view.len() as u64).saturating_sub(1)
+    }
+
+    /// Whether the line at `position` in the view is on screen.
+    fn is_on_screen(&self, position: usize) -> bool {
+        let top = self.line_num as usize;
+        (top..top + self.last_frame_height as usize).contains(&position)
+    }
+
+    /// Move the cursor by `offset` visible lines, scrolling to keep it on screen. A cursor that
+    /// isn't on screen starts from the top or bottom line instead.
+    pub fn move_cursor(&mut self, offset: isize) {
+        if self.view.len() == 0 {
+            return;
+        }
+        let top = self.line_num as usize;
+        let position = match self.cursor {
+            Some(index)
+                if self.view.contains(index) && self.is_on_screen(self.view.position_of(index)) =>
+            {
+                self.view.position_of(index).saturating_add_signed(offset)
+            }
+            _ if offset < 0 => top + (self.last_frame_height as usize).saturating_sub(1),
+            _ => top,
+        };
+        let position = position.min(self.view.len() - 1);
+        self.cursor = self.view.buffer_index(position);
+
+        if !self.is_on_screen(position) {
+            self.attached_to_bottom = false;
+            self.line_num = if position < top {
+                position
+            } else {
+                (position + 1).saturating_sub(self.last_frame_height as usize)
+            } as u64;
         }
     }
 
-    pub fn set_line_number(&mut self, line_number: u16) {
-        if line_number > self.lines.len() as u16 {
-            self.line_num = self.lines.len() as u16 - 1;
-        } else {
-            self.line_num = line_number;
+    /// The JSON in the line the cursor is on.
+    pub fn detail_value(&self) -> Option<Value> {
+        let line = self.lines.get_line(self.cursor?)?;
+        find_json(&line.text)
+    }
+
+    /// Look at the node at `index` in the detail pane's tree.
+    fn detail_node<T>(&self, index: usize, f: impl FnOnce(&Node) -> T) -> Option<T> {
+        let value = self.detail_value()?;
+        self.detail.nodes(&value).get(index).map(f)
+    }
+
+    /// Open the detail pane for the line the cursor is on, putting the cursor on the top line if
+    /// it isn't on screen.
+    pub fn open_detail(&mut self) {
+        let on_screen = self.cursor.is_some_and(|index| {
+            self.view.contains(index) && self.is_on_screen(self.view.position_of(index))
+        });
+        if !on_screen {
+            self.cursor = self.view.buffer_index(self.line_num as usize);
         }
+
+        let pane = MessageTranslator::new(ListSelectState::default(), |msg| match msg {
+            ListSelectMsg::Close | ListSelectMsg::Accept(_) => Some(AppAction::CloseDetail),
+            ListSelectMsg::Key(index, ' ') => Some(AppAction::ToggleDetailNode(index)),
+            ListSelectMsg::Key(index, 'y') => Some(AppAction::CopyDetailPath(index)),
+            ListSelectMsg::Key(index, 'i') => {
+                Some(AppAction::FilterDetailValue(index, FilterKind::Include))
+            }
+            ListSelectMsg::Key(index, 'x') => {
+                Some(AppAction::FilterDetailValue(index, FilterKind::Exclude))
+            }
+            ListSelectMsg::Key(_, 'J') => Some(AppAction::MoveCursor(1)),
+            ListSelectMsg::Key(_, 'K') => Some(AppAction::MoveCursor(-1)),
+            ListSelectMsg::Select(_) | ListSelectMsg::Key(_, _) => None,
+        });
+
+        let pane = Rc::new(Mutex::new(pane));
+        self.detail_pane = Some(pane.clone());
+        self.focused_input = Some(pane);
+        self.detail.copied = None;
+    }
+
+    pub fn close_detail(&mut self) {
+        self.detail_pane = None;
+        self.focused_input = None;
     }
 
     pub fn toggle_line_numbers(&mut self) {
@@ -137,44 +741,626 @@ impl AppState {
         let search_input = self
             .search
             .as_ref()
-            .map(|s| TextInputState::from_str(s))
-            .unwrap_or_default();
+            .map(|s| TextInputState::from_str(&s.pattern.source))
+            .unwrap_or_default()
+            .with_history(self.history.entries(HistoryKind::Search));
 
         let search_input = MessageTranslator::new(search_input, {
             |msg| match msg {
                 TextInputMsg::Close => Some(AppAction::CloseSearch),
                 TextInputMsg::Accept(input) => Some(AppAction::AcceptSearch(input)),
-                _ => None,
+                msg => pattern_prompt_action(msg),
             }
         });
 
         let search_input = Rc::new(Mutex::new(search_input));
         self.search_input = Some(search_input.clone());
         self.focused_input = Some(search_input);
+        self.search_origin = Some(SearchOrigin {
+            top: self.top_index(),
+            attached: self.attached_to_bottom,
+            search: self.search.clone(),
+        });
     }
 
     pub fn close_search(&mut self) {
         self.search_input = None;
         self.focused_input = None;
+        self.search_origin = None;
     }
 
-    pub fn open_go_to_line(&mut self) {
-        let go_to_line = MessageTranslator::new(TextInputState::default(), |msg| match msg {
-            TextInputMsg::Close => Some(AppAction::CloseGoToLine),
-            TextInputMsg::Accept(input) => {
-                if let Ok(line_number) = input.parse::<u16>() {
-                    Some(AppAction::AcceptGoToLine(line_number))
-                } else {
-                    None
+    /// Close the search prompt, going back to the search and position from before it was opened.
+    pub fn cancel_search(&mut self) {
+        if let Some(origin) = self.search_origin.take() {
+            self.search = origin.search;
+            self.restore_position(origin.top, origin.attached);
+        }
+        self.close_search();
+    }
+
+    /// Show the matches for a search that is still being typed, and jump to the first one from
+    /// where the search prompt was opened.
+    fn preview_search(&mut self, pattern: Option<Pattern>) {
+        let Some(&SearchOrigin { top, attached, .. }) = self.search_origin.as_ref() else {
+            return;
+        };
+
+        // Stay where the search started until a match is found.
+        self.restore_position(top, attached);
+        self.search =
+            pattern.map(|pattern| Search::new(pattern, self.tab_width, &self.lines, &self.view));
+        if let Some(search) = self.search.as_mut() {
+            search.jump_from(top);
+        }
+        self.follow_search_jump();
+    }
+
+    pub fn accept_search(&mut self, query: String) -> Result<(), regex::Error> {
+        if query.is_empty() {
+            self.search = None;
+            return Ok(());
+        }
+
+        // The search is usually already there from being previewed.
+        let previewed = self.search.as_ref().is_some_and(|search| {
+            search.pattern.source == query && search.pattern.options == self.search_options
+        });
+        if !previewed {
+            let pattern = Pattern::new(query, self.search_options)?;
+            let mut search = Search::new(pattern, self.tab_width, &self.lines, &self.view);
+            search.jump_from(self.top_index());
+            self.search = Some(search);
+            self.follow_search_jump();
+        }
+        if let Some(search) = self.search.as_ref() {
+            self.history
+                .add(HistoryKind::Search, &search.pattern.source);
+        }
+        Ok(())
+    }
+
+    /// Jump to the match the search was asked to jump to, once it has been found.
+    fn follow_search_jump(&mut self) {
+        if let Some(target) = self.search.as_mut().and_then(Search::take_jump) {
+            self.attached_to_bottom = false;
+            self.scroll_to_index(target as u64);
+        }
+    }
+
+    /// Stop a search that is running in the background, keeping the matches found so far.
+    pub fn cancel_background_search(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            search.cancel(&self.view);
+        }
+    }
+
+    pub fn open_results(&mut self) {
+        let Some(search) = self.search.as_ref() else {
+            return;
+        };
+
+        let state = ListSelectState::new(search.current.unwrap_or(0));
+        let pane = MessageTranslator::new(state, |msg| match msg {
+            ListSelectMsg::Close | ListSelectMsg::Key(_, 'R') => Some(AppAction::CloseResults),
+            ListSelectMsg::Select(index) => Some(AppAction::SelectResult(index)),
+            ListSelectMsg::Accept(index) => Some(AppAction::AcceptResult(index)),
+            ListSelectMsg::Key(_, _) => None,
+        });
+
+        let pane = Rc::new(Mutex::new(pane));
+        self.results_pane = Some(pane.clone());
+        self.focused_input = Some(pane);
+    }
+
+    pub fn close_results(&mut self) {
+        self.results_pane = None;
+        self.focused_input = None;
+    }
+
+    /// Jump to a match picked from the results pane.
+    fn select_result(&mut self, index: usize) {
+        let target = self.search.as_mut().and_then(|search| search.select(index));
+        if let Some(target) = target {
+            self.attached_to_bottom = false;
+            self.scroll_to_index(target as u64);
+        }
+    }
+
+    pub fn next_match(&mut self) {
+        self.jump_to_match(Search::next_after);
+    }
+
+    pub fn prev_match(&mut self) {
+        self.jump_to_match(Search::prev_before);
+    }
+
+    fn jump_to_match(&mut self, find: impl FnOnce(&mut Search, usize) -> Option<usize>) {
+        let top = self.top_index();
+        let target = self.search.as_mut().and_then(|search| find(search, top));
+        if let Some(target) = target {
+            self.attached_to_bottom = false;
+            self.scroll_to_index(target as u64);
+        }
+    }
+
+    pub fn open_filter(&mut self, target: FilterTarget) {
+        let filter_input = match target {
+            FilterTarget::New(_) => TextInputState::default(),
+            FilterTarget::Existing(index) => match self.filters.filters.get(index) {
+                Some(filter) => {
+                    self.filter_options = filter.pattern.options;
+                    TextInputState::from_str(&filter.pattern.source)
                 }
+                None => return,
+            },
+        }
+        .with_history(self.history.entries(HistoryKind::Filter));
+
+        let filter_input = MessageTranslator::new(filter_input, move |msg| match msg {
+            TextInputMsg::Close => Some(AppAction::CloseFilter),
+            TextInputMsg::Accept(input) => Some(AppAction::AcceptFilter(target, input)),
+            msg => pattern_prompt_action(msg),
+        });
+
+        let filter_input = Rc::new(Mutex::new(filter_input));
+        self.filter_input = Some(filter_input.clone());
+        self.focused_input = Some(filter_input);
+    }
+
+    pub fn close_filter(&mut self) {
+        self.filter_input = None;
+        // Go back to the panel if the prompt was opened from it.
+        self.focused_input = self
+            .filter_panel
+            .clone()
+            .map(|panel| panel as InputHandlerRc);
+    }
+
+    /// Add a filter or change the pattern of an existing one. An empty pattern removes it.
+    pub fn accept_filter(
+        &mut self,
+        target: FilterTarget,
+        pattern: String,
+    ) -> Result<(), regex::Error> {
+        let pattern = match pattern.as_str() {
+            "" => None,
+            _ => Some(Pattern::new(pattern, self.filter_options)?),
+        };
+        if let Some(pattern) = pattern.as_ref() {
+            self.history.add(HistoryKind::Filter, &pattern.source);
+        }
+
+        self.update_filters(|chain| match (target, pattern) {
+            (FilterTarget::New(_), None) => {}
+            (FilterTarget::New(kind), Some(pattern)) => {
+                chain.filters.push(Filter::new(kind, pattern))
+            }
+            (FilterTarget::Existing(index), _) if index >= chain.filters.len() => {}
+            (FilterTarget::Existing(index), None) => {
+                chain.filters.remove(index);
+            }
+            (FilterTarget::Existing(index), Some(pattern)) => {
+                chain.filters[index].pattern = pattern
+            }
+        });
+        Ok(())
+    }
+
+    pub fn open_query(&mut self) {
+        self.discover_fields();
+        let fields = self
+            .columns
+            .columns
+            .iter()
+            .map(|column| column.field.clone())
+            .collect();
+        let source = self
+            .filters
+            .query
+            .as_ref()
+            .map_or("", |query| query.source.as_str());
+        let query_input = TextInputState::from_str(source)
+            .with_history(self.history.entries(HistoryKind::Query))
+            .with_completions(fields);
+
+        let query_input = MessageTranslator::new(query_input, |msg| match msg {
+            TextInputMsg::Close => Some(AppAction::CloseQuery),
+            TextInputMsg::Accept(input) => Some(AppAction::AcceptQuery(input)),
+            TextInputMsg::Change(input) => Some(AppAction::QueryChanged(input)),
+            TextInputMsg::Alt(_) => None,
+        });
+
+        let query_input = Rc::new(Mutex::new(query_input));
+        self.query_input = Some(query_input.clone());
+        self.focused_input = Some(query_input);
+    }
+
+    pub fn close_query(&mut self) {
+        self.query_input = None;
+        self.focused_input = None;
+    }
+
+    /// Filter on the fields of lines with a query. An empty query removes it.
+    pub fn accept_query(&mut self, source: &str) -> Result<(), QueryError> {
+        let query = match source.trim() {
+            "" => None,
+            _ => Some(Query::parse(source, self.time_reference())?),
+        };
+        if query.is_some() {
+            self.history.add(HistoryKind::Query, source);
+        }
+        self.update_filters(|chain| chain.query = query);
+        Ok(())
+    }
+
+    /// Point out what's wrong with the query being typed, if anything.
+    fn query_changed(&mut self, source: &str) {
+        let Some(input) = self.query_input.as_ref() else {
+            return;
+        };
+        let error = match source.trim() {
+            "" => None,
+            _ => Query::parse(source, self.time_reference()).err(),
+        };
+        let mut input = input.lock().unwrap();
+        let input = input.input_handler_mut();
+        input.color = if error.is_some() {
+            Color::Red
+        } else {
+            Color::Reset
+        };
+        input.error = error.map(|error| (error.column, error.message));
+    }
+
+    pub fn open_filter_panel(&mut self) {
+        self.column_panel = None;
+        self.facet_panel = None;
+        let panel = MessageTranslator::new(ListSelectState::default(), |msg| match msg {
+            ListSelectMsg::Close | ListSelectMsg::Key(_, 'F') => Some(AppAction::CloseFilterPanel),
+            ListSelectMsg::Accept(index) | ListSelectMsg::Key(index, 'e') => {
+                Some(AppAction::OpenFilter(FilterTarget::Existing(index)))
+            }
+            ListSelectMsg::Key(_, 'i' | '&') => Some(AppAction::OpenFilter(FilterTarget::New(
+                FilterKind::Include,
+            ))),
+            ListSelectMsg::Key(_, 'x') => Some(AppAction::OpenFilter(FilterTarget::New(
+                FilterKind::Exclude,
+            ))),
+            ListSelectMsg::Key(index, ' ') => Some(AppAction::ToggleFilter(index)),
+            ListSelectMsg::Key(index, 'v') => Some(AppAction::InvertFilter(index)),
+            ListSelectMsg::Key(index, 'd') => Some(AppAction::RemoveFilter(index)),
+            ListSelectMsg::Key(_, 'C') => Some(AppAction::ClearFilters),
+            ListSelectMsg::Select(_) | ListSelectMsg::Key(_, _) => None,
+        });
+
+        let panel = Rc::new(Mutex::new(panel));
+        self.filter_panel = Some(panel.clone());
+        self.focused_input = Some(panel);
+    }
+
+    pub fn close_filter_panel(&mut self) {
+        self.filter_panel = None;
+        self.focused_input = None;
+    }
+
+    pub fn open_column_panel(&mut self) {
+        self.filter_panel = None;
+        self.facet_panel = None;
+        let panel = MessageTranslator::new(ListSelectState::default(), |msg| match msg {
+            ListSelectMsg::Close | ListSelectMsg::Key(_, 'C') => Some(AppAction::CloseColumnPanel),
+            ListSelectMsg::Accept(index) | ListSelectMsg::Key(index, ' ') => {
+                Some(AppAction::ToggleColumn(index))
+            }
+            ListSelectMsg::Key(index, 'K') => Some(AppAction::MoveColumn(index, -1)),
+            ListSelectMsg::Key(index, 'J') => Some(AppAction::MoveColumn(index, 1)),
+            ListSelectMsg::Key(index, '-') => Some(AppAction::ResizeColumn(index, -1)),
+            ListSelectMsg::Key(index, '+' | '=') => Some(AppAction::ResizeColumn(index, 1)),
+            ListSelectMsg::Key(index, 'f') => Some(AppAction::FillColumn(index)),
+            ListSelectMsg::Key(_, 'T') => Some(AppAction::ToggleTableView),
+            ListSelectMsg::Select(_) | ListSelectMsg::Key(_, _) => None,
+        });
+
+        let panel = Rc::new(Mutex::new(panel));
+        self.column_panel = Some(panel.clone());
+        self.focused_input = Some(panel);
+    }
+
+    pub fn close_column_panel(&mut self) {
+        self.column_panel = None;
+        self.focused_input = None;
+    }
+
+    pub fn open_facet_panel(&mut self) {
+        self.filter_panel = None;
+        self.column_panel = None;
+        let panel = MessageTranslator::new(ListSelectState::default(), |msg| match msg {
+            ListSelectMsg::Close | ListSelectMsg::Key(_, 'f') => Some(AppAction::CloseFacetPanel),
+            ListSelectMsg::Accept(index) | ListSelectMsg::Key(index, ' ' | 'i') => {
+                Some(AppAction::ToggleFacet(index, FilterKind::Include))
             }
-            TextInputMsg::Change(input) => {
-                if let Ok(_) = input.parse::<u16>() {
-                    Some(AppAction::SetGoToLineColor(Color::Reset))
-                } else {
-                    Some(AppAction::SetGoToLineColor(Color::Red))
+            ListSelectMsg::Key(index, 'x') => {
+                Some(AppAction::ToggleFacet(index, FilterKind::Exclude))
+            }
+            ListSelectMsg::Select(_) | ListSelectMsg::Key(_, _) => None,
+        });
+
+        let panel = Rc::new(Mutex::new(panel));
+        self.facet_panel = Some(panel.clone());
+        self.focused_input = Some(panel);
+    }
+
+    pub fn close_facet_panel(&mut self) {
+        self.facet_panel = None;
+        self.focused_input = None;
+    }
+
+    pub fn open_histogram(&mut self) {
+        self.count_mapped_lines();
+        self.histogram.update(self.histogram_width as usize);
+        // Start from the latest lines.
+        let state = BarSelectState::new(self.histogram.buckets.len().saturating_sub(1));
+        let pane = MessageTranslator::new(state, |msg| match msg {
+            BarSelectMsg::Close | BarSelectMsg::Key(_, 'H') => Some(AppAction::CloseHistogram),
+            BarSelectMsg::Select(index) => Some(AppAction::SelectBucket(index)),
+            BarSelectMsg::Accept(index) => Some(AppAction::AcceptBucket(index)),
+            BarSelectMsg::Key(index, 'r') => Some(AppAction::RestrictToBucket(index)),
+            BarSelectMsg::Key(_, 'c') => Some(AppAction::ClearTimeRange),
+            BarSelectMsg::Key(_, _) => None,
+        });
+
+        let pane = Rc::new(Mutex::new(pane));
+        self.histogram_pane = Some(pane.clone());
+        self.focused_input = Some(pane);
+    }
+
+    pub fn close_histogram(&mut self) {
+        self.histogram_pane = None;
+        self.focused_input = None;
+    }
+
+    /// Jump to the first line of a bucket picked from the histogram.
+    fn select_bucket(&mut self, index: usize) {
+        let first_index = self
+            .histogram
+            .buckets
+            .get(index)
+            .and_then(|bucket| bucket.first_index);
+        if let Some(first_index) = first_index {
+            self.jump_to_index(first_index);
+        }
+    }
+
+    /// Scroll to the buffer line at `index`, or the first visible line after it, and put the
+    /// cursor on it.
+    fn jump_to_index(&mut self, index: usize) {
+        self.attached_to_bottom = false;
+        self.scroll_to_index(index as u64);
+        self.cursor = self
+            .view
+            .buffer_index(self.view.position_of(index))
+            .or(self.cursor);
+    }
+
+    /// The time that relative times and times of day typed into prompts go by: the timestamp of
+    /// the last line, so they work the same in old logs, or now if no line has one.
+    fn time_reference(&self) -> NaiveDateTime {
+        self.lines
+            .last_timestamp()
+            .unwrap_or_else(|| Local::now().naive_local())
+    }
+
+    pub fn open_go_to_time(&mut self) {
+        let go_to_time =
+            TextInputState::default().with_history(self.history.entries(HistoryKind::GoToTime));
+        let go_to_time = MessageTranslator::new(go_to_time, |msg| match msg {
+            TextInputMsg::Close => Some(AppAction::CloseGoToTime),
+            TextInputMsg::Accept(input) => Some(AppAction::AcceptGoToTime(input)),
+            TextInputMsg::Change(input) => Some(AppAction::TimePromptChanged(input)),
+            TextInputMsg::Alt(_) => None,
+        });
+
+        let go_to_time = Rc::new(Mutex::new(go_to_time));
+        self.go_to_time = Some(go_to_time.clone());
+        self.focused_input = Some(go_to_time);
+    }
+
+    pub fn close_go_to_time(&mut self) {
+        self.go_to_time = None;
+        self.focused_input = None;
+    }
+
+    /// Jump to the line logged closest to a time typed into the go-to-time prompt.
+    pub fn accept_go_to_time(&mut self, input: &str) -> Result<(), String> {
+        let time = parse_time_from(input, self.time_reference())
+            .ok_or("expected a time like 14:03, 2026-10-17 14:03:22, -15m or 5m ago")?;
+        let index = self
+            .lines
+            .find_time(time)
+            .ok_or("no lines have timestamps")?;
+        self.history.add(HistoryKind::GoToTime, input.trim());
+        self.jump_to_index(index);
+        Ok(())
+    }
+
+    pub fn open_time_range(&mut self) {
+        let time_range =
+            TextInputState::default().with_history(self.history.entries(HistoryKind::TimeRange));
+        let time_range = MessageTranslator::new(time_range, |msg| match msg {
+            TextInputMsg::Close => Some(AppAction::CloseTimeRange),
+            TextInputMsg::Accept(input) => Some(AppAction::AcceptTimeRange(input)),
+            TextInputMsg::Change(input) => Some(AppAction::TimePromptChanged(input)),
+            TextInputMsg::Alt(_) => None,
+        });
+
+        let time_range = Rc::new(Mutex::new(time_range));
+        self.time_range_input = Some(time_range.clone());
+        self.focused_input = Some(time_range);
+    }
+
+    pub fn close_time_range(&mut self) {
+        self.time_range_input = None;
+        self.focused_input = None;
+    }
+
+    /// Only show lines logged in a range typed into the time range prompt. An empty range shows
+    /// every line again.
+    pub fn accept_time_range(&mut self, input: &str) -> Result<(), String> {
+        let range = match input.trim() {
+            "" => None,
+            input => Some(parse_time_range(input, self.time_reference())?),
+        };
+        if range.is_some() {
+            self.history.add(HistoryKind::TimeRange, input.trim());
+        }
+        self.set_time_range(range);
+        Ok(())
+    }
+
+    /// Only show lines with a timestamp in `range`, or every line if there's none.
+    pub fn set_time_range(&mut self, range: Option<Range<NaiveDateTime>>) {
+        self.update_filters(|chain| chain.time_range = range);
+    }
+
+    /// Color the time being typed into the go-to-time or time range prompt red until it's valid.
+    fn time_prompt_changed(&mut self, input: &str) {
+        let now = self.time_reference();
+        let (prompt, valid) = match (self.go_to_time.as_ref(), self.time_range_input.as_ref()) {
+            (Some(prompt), _) => (prompt, parse_time_from(input, now).is_some()),
+            (None, Some(prompt)) => (
+                prompt,
+                input.trim().is_empty() || parse_time_range(input, now).is_ok(),
+            ),
+            (None, None) => return,
+        };
+        let mut prompt = prompt.lock().unwrap();
+        let prompt = prompt.input_handler_mut();
+        prompt.color = if valid { Color::Reset } else { Color::Red };
+        prompt.error = None;
+    }
+
+    /// Point out why what was entered into a prompt wasn't accepted.
+    fn set_prompt_error(prompt: Option<&MessageTranslatorRc<TextInputState>>, message: String) {
+        if let Some(prompt) = prompt {
+            let mut prompt = prompt.lock().unwrap();
+            let prompt = prompt.input_handler_mut();
+            prompt.color = Color::Red;
+            prompt.error = Some((0, message));
+        }
+    }
+
+    /// Make a change to the filter chain and update the view to match. The position from before
+    /// the first filter was enabled is restored once no filters are enabled.
+    fn update_filters(&mut self, change: impl FnOnce(&mut FilterChain)) {
+        let position = (self.top_index(), self.attached_to_bottom);
+        let was_active = self.filters.is_active();
+
+        change(&mut self.filters);
+        self.rebuild_view();
+
+        match (was_active, self.filters.is_active()) {
+            (false, true) => self.unfiltered_position = Some(position),
+            (true, false) => {
+                if let Some((top, attached)) = self.unfiltered_position.take() {
+                    self.restore_position(top, attached);
                 }
             }
+            _ => {}
+        }
+    }
+
+    fn restore_position(&mut self, top: usize, attached: bool) {
+        if attached {
+            self.attach_to_bottom();
+        } else {
+            self.attached_to_bottom = false;
+            self.scroll_to_index(top as u64);
+        }
+    }
+
+    pub fn toggle_source(&mut self, source: usize) {
+        let Some(source) = self.sources.get_mut(source) else {
+            return;
+        };
+        source.visible = !source.visible;
+        self.rebuild_view();
+    }
+
+    /// Recompute which lines are visible from scratch, keeping the top line in place if it is
+    /// still visible.
+    fn rebuild_view(&mut self) {
+        let top = self.top_index();
+
+        self.view = if !self.filters.is_active() && self.sources.iter().all(|source| source.visible)
+        {
+            View::all()
+        } else {
+            View::filtered()
+        };
+        if let Some(search) = self.search.as_mut() {
+            search.view_changed();
+        }
+        self.sync_lines();
+
+        if !self.attached_to_bottom {
+            self.scroll_to_index(top as u64);
+        }
+    }
+
+    /// The pattern prompt that is open, if any, along with the options it uses.
+    fn pattern_prompt(
+        &mut self,
+    ) -> Option<(MessageTranslatorRc<TextInputState>, &mut PatternOptions)> {
+        if let Some(input) = self.filter_input.clone() {
+            Some((input, &mut self.filter_options))
+        } else {
+            let input = self.search_input.clone()?;
+            Some((input, &mut self.search_options))
+        }
+    }
+
+    pub fn toggle_pattern_option(&mut self, toggle: PatternToggle) {
+        if let Some((_, options)) = self.pattern_prompt() {
+            options.toggle(toggle);
+        }
+        self.pattern_changed();
+    }
+
+    /// Handle the text or options of the open pattern prompt changing. The prompt is colored red
+    /// if it doesn't hold a valid pattern, and a search is previewed as it is typed.
+    pub fn pattern_changed(&mut self) {
+        let Some((input, options)) = self.pattern_prompt() else {
+            return;
+        };
+        let options = *options;
+
+        let pattern = {
+            let mut input = input.lock().unwrap();
+            let input = input.input_handler_mut();
+            let pattern = match input.input.value() {
+                "" => Ok(None),
+                value => Pattern::new(value.to_string(), options).map(Some),
+            };
+            input.color = if pattern.is_ok() {
+                Color::Reset
+            } else {
+                Color::Red
+            };
+            pattern
+        };
+
+        if let (None, Ok(pattern)) = (self.filter_input.as_ref(), pattern) {
+            self.preview_search(pattern);
+        }
+    }
+
+    pub fn open_go_to_line(&mut self) {
+        let go_to_line =
+            TextInputState::default().with_history(self.history.entries(HistoryKind::GoToLine));
+        let go_to_line = MessageTranslator::new(go_to_line, |msg| match msg {
+            TextInputMsg::Close => Some(AppAction::CloseGoToLine),
+            TextInputMsg::Accept(input) => Some(AppAction::AcceptGoToLine(input)),
+            TextInputMsg::Change(input) => Some(AppAction::GoToLineChanged(input)),
+            TextInputMsg::Alt(_) => None,
         });
 
         let go_to_line = Rc::new(Mutex::new(go_to_line));
@@ -184,14 +1370,607 @@ impl AppState {
 
     pub fn close_go_to_line(&mut self) {
         self.go_to_line = None;
+        self.go_to_line_preview = None;
         self.focused_input = None;
     }
 
+    /// Jump to the line typed into the go-to-line prompt.
+    pub fn accept_go_to_line(&mut self, input: &str) -> Result<(), String> {
+        let index = self.find_line_target(input)?;
+        self.history.add(HistoryKind::GoToLine, input.trim());
+        self.jump_to_index(index);
+        Ok(())
+    }
+
+    /// Preview the line the go-to-line prompt would go to, coloring it red if there's none.
+    fn go_to_line_changed(&mut self, input: &str) {
+        let target = self.find_line_target(input).ok();
+        let color = match target {
+            Some(_) => Color::Reset,
+            None if input.trim().is_empty() => Color::Reset,
+            None => Color::Red,
+        };
+        self.set_go_to_line_color(color);
+        self.go_to_line_preview = target;
+    }
+
+    /// The buffer index of the visible line that `input` to the go-to-line prompt refers to.
+    /// Lines that are hidden are passed for the next visible line.
+    fn find_line_target(&self, input: &str) -> Result<usize, String> {
+        let target = LineTarget::parse(input, self.time_reference())
+            .ok_or("expected a line number, +N, -N, N%, $, 'x or a time")?;
+        let last = self.view.len().checked_sub(1).ok_or("no lines to go to")?;
+        let position = match target {
+            LineTarget::Line(line) => {
+                let index = usize::try_from(line.saturating_sub(1)).unwrap_or(usize::MAX);
+                self.view.position_of(index)
+            }
+            LineTarget::Offset(offset) => (self.line_num as usize)
+                .saturating_add_signed(offset.try_into().unwrap_or(isize::MAX)),
+            LineTarget::Percent(percent) => (last as f64 * percent / 100.0).round() as usize,
+            LineTarget::Last => last,
+            LineTarget::Mark(mark) => {
+                let index = self
+                    .marks
+                    .get(&mark)
+                    .ok_or_else(|| format!("no line is marked '{}", mark))?;
+                self.view.position_of(*index)
+            }
+            LineTarget::Time(time) => {
+                let index = self
+                    .lines
+                    .find_time(time)
+                    .ok_or("no lines have timestamps")?;
+                self.view.position_of(index)
+            }
+        };
+        Ok(self.view.buffer_index(position.min(last)).unwrap_or(0))
+    }
+
     pub fn set_go_to_line_color(&mut self, color: Color) {
         if let Some(go_to_line) = self.go_to_line.as_ref() {
             let mut go_to_line = go_to_line.lock().unwrap();
             let go_to_line = go_to_line.input_handler_mut();
             go_to_line.color = color;
+            go_to_line.error = None;
+        }
+    }
+
+    /// Mark the line the cursor is on, or the top line if the cursor isn't on screen, so it can
+    /// be gone back to by entering `'` and the mark into the go-to-line prompt.
+    pub fn set_mark(&mut self, mark: char) {
+        let cursor = self.cursor.filter(|&index| {
+            self.view.contains(index) && self.is_on_screen(self.view.position_of(index))
+        });
+        if let Some(index) = cursor.or_else(|| self.view.buffer_index(self.line_num as usize)) {
+            self.marks.insert(mark, index);
         }
     }
 }
+
+/// Map the messages that every pattern prompt handles the same way.
+fn pattern_prompt_action(msg: TextInputMsg) -> Option<AppAction> {
+    match msg {
+        TextInputMsg::Change(_) => Some(AppAction::PatternChanged),
+        TextInputMsg::Alt('r') => Some(AppAction::TogglePatternOption(PatternToggle::Regex)),
+        TextInputMsg::Alt('c') => Some(AppAction::TogglePatternOption(PatternToggle::Case)),
+        TextInputMsg::Alt('w') => Some(AppAction::TogglePatternOption(PatternToggle::WholeWord)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with_lines(count: u64) -> AppState {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 40;
+        for i in 1..=count {
+            state.add_line(LogLine::new(0, format!("line {}", i)));
+        }
+        state.sync_lines();
+        state
+    }
+
+    #[test]
+    fn test_scrolling_past_u16_range() {
+        let mut state = state_with_lines(2_000_000);
+
+        state.scroll_down_by(70_000);
+        assert_eq!(state.line_num, 70_000);
+        assert_eq!(state.get_lines()[0].1.text, "line 70001");
+
+        state.scroll_down_by(u64::MAX);
+        assert_eq!(state.line_num, 1_999_999);
+
+        state.scroll_up_by(1_000_000);
+        assert_eq!(state.line_num, 999_999);
+
+        state.scroll_up_by(u64::MAX);
+        assert_eq!(state.line_num, 0);
+    }
+
+    #[test]
+    fn test_go_to_line_past_u16_range() {
+        let mut state = state_with_lines(1_500_000);
+
+        state.handle_action(AppAction::AcceptGoToLine("1234567".to_string()));
+        assert_eq!(state.get_lines()[0].1.text, "line 1234567");
+
+        state.handle_action(AppAction::AcceptGoToLine("9000000000".to_string()));
+        let lines = state.get_lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].1.text, "line 1500000");
+    }
+
+    #[test]
+    fn test_attach_to_bottom_follows_new_lines() {
+        let mut state = state_with_lines(1_000_000);
+
+        state.attach_to_bottom();
+        assert_eq!(state.line_num, 999_960);
+
+        state.add_line(LogLine::new(0, "line 1000001"));
+        state.sync_lines();
+        assert_eq!(state.line_num, 999_961);
+        assert_eq!(state.get_lines().last().unwrap().1.text, "line 1000001");
+    }
+
+    #[test]
+    fn test_scrolling_empty_buffer() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.scroll_down_by(10);
+        state.attach_to_bottom();
+        state.set_line_number(5);
+        assert_eq!(state.line_num, 0);
+        assert!(state.get_lines().is_empty());
+    }
+
+    #[test]
+    fn test_toggle_source_visibility() {
+        let mut state = AppState::new(vec!["api.log".to_string(), "worker.log".to_string()]);
+        state.last_frame_height = 40;
+        for i in 1..=10 {
+            state.add_line(LogLine::new(i % 2, format!("line {}", i)));
+        }
+        state.sync_lines();
+        state.accept_search("line 1".to_string()).unwrap();
+        assert_eq!(state.search.as_ref().unwrap().matches, vec![0, 9]);
+
+        state.toggle_source(0);
+        let lines = state.get_lines();
+        assert_eq!(lines.len(), 5);
+        assert!(lines.iter().all(|(_, line)| line.source == 1));
+        assert_eq!(lines[0].0, 0);
+        assert_eq!(state.search.as_ref().unwrap().matches, vec![0]);
+
+        state.add_line(LogLine::new(0, "line 11"));
+        state.add_line(LogLine::new(1, "line 12"));
+        state.sync_lines();
+        assert_eq!(state.view.len(), 6);
+
+        state.set_line_number(4);
+        assert_eq!(state.get_lines()[0].1.text, "line 5");
+
+        state.toggle_source(0);
+        assert_eq!(state.view.len(), 12);
+        assert_eq!(state.get_lines()[0].1.text, "line 5");
+    }
+
+    #[test]
+    fn test_filter_chain_keeps_line_numbers_and_restores_position() {
+        let mut state = state_with_lines(100);
+        state.last_frame_height = 5;
+        state.set_line_number(50);
+
+        state
+            .accept_filter(FilterTarget::New(FilterKind::Include), "7".to_string())
+            .unwrap();
+        // 7, 17, 27, ... 97 and 70 through 79.
+        assert_eq!(state.view.len(), 19);
+        let lines = state.get_lines();
+        assert_eq!(lines[0], (56, LogLine::new(0, "line 57")));
+
+        state.add_line(LogLine::new(0, "line 107"));
+        state.add_line(LogLine::new(0, "line 108"));
+        state.sync_lines();
+        assert_eq!(state.view.len(), 20);
+
+        state.attach_to_bottom();
+        assert_eq!(
+            state.get_lines().last(),
+            Some(&(100, LogLine::new(0, "line 107")))
+        );
+        state.set_line_number(72);
+        assert_eq!(state.get_lines()[0].1.text, "line 72");
+        state.set_line_number(81);
+        assert_eq!(state.get_lines()[0].1.text, "line 87");
+
+        state
+            .accept_filter(FilterTarget::New(FilterKind::Exclude), "line 7".to_string())
+            .unwrap();
+        // 7 and 70 through 79 are gone; 17 through 97 (apart from 77) and 107 remain.
+        assert_eq!(state.view.len(), 9);
+
+        state.handle_action(AppAction::ToggleFilter(0));
+        assert_eq!(state.view.len(), 102 - 11);
+        state.handle_action(AppAction::ToggleFilter(1));
+        assert_eq!(state.view.len(), 102);
+        assert_eq!(state.get_lines()[0].1.text, "line 50");
+    }
+
+    #[test]
+    fn test_search_preview() {
+        let mut state = state_with_lines(100);
+        state.last_frame_height = 5;
+        state.set_line_number(50);
+        let pattern = |query: &str| Pattern::new(query.to_string(), PatternOptions::default()).ok();
+
+        state.open_search();
+        state.preview_search(pattern("line 7"));
+        assert_eq!(state.get_lines()[0].1.text, "line 70");
+        state.preview_search(pattern("line 99"));
+        assert_eq!(state.get_lines()[0].1.text, "line 99");
+        state.preview_search(pattern("no such line"));
+        assert_eq!(state.get_lines()[0].1.text, "line 50");
+
+        state.preview_search(pattern("line 99"));
+        state.handle_action(AppAction::CloseSearch);
+        assert!(state.search.is_none());
+        assert_eq!(state.get_lines()[0].1.text, "line 50");
+
+        state.open_search();
+        state.preview_search(pattern("line 2"));
+        state.handle_action(AppAction::AcceptSearch("line 2".to_string()));
+        assert!(state.search_input.is_none());
+        assert_eq!(state.search.as_ref().unwrap().matches.len(), 11);
+        assert_eq!(state.get_lines()[0].1.text, "line 2");
+    }
+
+    #[test]
+    fn test_filters_and_search_match_displayed_text() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 10;
+        state.add_line(LogLine::new(0, "\x1b[31mERR\x1b[0mOR: colored"));
+        state.add_line(LogLine::new(0, "\x1b[31minfo"));
+        state.add_line(LogLine::new(0, "\tindented"));
+        state.sync_lines();
+
+        state.accept_search("ERROR".to_string()).unwrap();
+        assert_eq!(state.search.as_ref().unwrap().matches, vec![0]);
+        state
+            .accept_filter(FilterTarget::New(FilterKind::Exclude), "ERROR".to_string())
+            .unwrap();
+        assert!(!state.view.contains(0));
+        assert_eq!(state.view.len(), 2);
+
+        // Escape sequences and tabs are matched as displayed.
+        state
+            .accept_filter(FilterTarget::New(FilterKind::Exclude), r"\[31m".to_string())
+            .unwrap();
+        assert_eq!(state.view.len(), 2);
+        state
+            .accept_filter(
+                FilterTarget::New(FilterKind::Include),
+                " {8}indented".to_string(),
+            )
+            .unwrap();
+        assert_eq!(state.view.len(), 1);
+    }
+
+    #[test]
+    fn test_results_pane() {
+        let mut state = state_with_lines(100);
+        state.last_frame_height = 5;
+        state.handle_action(AppAction::OpenResults);
+        assert!(state.results_pane.is_none());
+
+        state.accept_search("line 3".to_string()).unwrap();
+        state.handle_action(AppAction::OpenResults);
+        assert!(state.results_pane.is_some());
+
+        state.handle_action(AppAction::AcceptResult(4));
+        assert!(state.results_pane.is_none());
+        assert!(state.focused_input.is_none());
+        assert_eq!(state.search.as_ref().unwrap().current, Some(4));
+        assert_eq!(state.get_lines()[0].1.text, "line 33");
+    }
+
+    #[test]
+    fn test_cursor_and_detail() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 3;
+        for i in 1..=10 {
+            let status = if i % 3 == 0 { 500 } else { 200 };
+            state.add_line(LogLine::new(
+                0,
+                format!(
+                    r#"request {} {{"req": {{"id": {}}}, "status": {}}}"#,
+                    i, i, status
+                ),
+            ));
+        }
+        state.sync_lines();
+
+        state.handle_action(AppAction::MoveCursor(-1));
+        assert_eq!(state.cursor, Some(2));
+        state.handle_action(AppAction::MoveCursor(1));
+        assert_eq!(state.cursor, Some(3));
+        assert_eq!(state.line_num, 1);
+
+        state.handle_action(AppAction::OpenDetail);
+        assert_eq!(state.detail_value().unwrap()["req"]["id"], 4);
+        state.handle_action(AppAction::ToggleDetailNode(0));
+        assert_eq!(
+            state.detail_node(1, |node| node.path.clone()).unwrap(),
+            ".status"
+        );
+
+        state.handle_action(AppAction::MoveCursor(-1));
+        state.handle_action(AppAction::FilterDetailValue(1, FilterKind::Include));
+        assert_eq!(state.view.len(), 3);
+        state.handle_action(AppAction::CloseDetail);
+        assert!(state.focused_input.is_none());
+    }
+
+    #[test]
+    fn test_filter_detail_value() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 10;
+        for text in [
+            r#"done {"status": 50, "req": {"id": 7}}"#,
+            r#"done {"status": 500, "req": {"id": 8}}"#,
+            r#"done {"status": 500, "id": 7, "req": {"id": 9}}"#,
+            r#"done {"status":50,"req":{"id":7.0}}"#,
+            r#"done {"req": {"status": 50}}"#,
+        ] {
+            state.add_line(LogLine::new(0, text));
+        }
+        state.sync_lines();
+        state.cursor = Some(0);
+        state.handle_action(AppAction::OpenDetail);
+
+        state.handle_action(AppAction::FilterDetailValue(0, FilterKind::Include));
+        assert_eq!(state.view.len(), 2);
+        assert_eq!(state.get_lines()[1].0, 3);
+        state.handle_action(AppAction::FilterDetailValue(0, FilterKind::Include));
+        assert_eq!(state.view.len(), 5);
+
+        // Only `id` under `req` counts, and `7.0` is a different number than `7` in JSON.
+        state.handle_action(AppAction::FilterDetailValue(2, FilterKind::Exclude));
+        assert_eq!(state.view.len(), 4);
+        assert!(state.view.contains(2));
+        assert!(state.view.contains(3));
+        assert!(!state.view.contains(0));
+    }
+
+    #[test]
+    fn test_query_filter() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 10;
+        state.set_formats(vec![LogFormat::Logfmt], None);
+        for i in 1..=20 {
+            let level = if i % 4 == 0 { "error" } else { "info" };
+            state.add_line(LogLine::new(
+                0,
+                format!("level={} component=api latency_ms={}", level, i * 10),
+            ));
+        }
+        state.sync_lines();
+
+        state.handle_action(AppAction::OpenQuery);
+        state.handle_action(AppAction::QueryChanged("level >= ".to_string()));
+        let error = |state: &AppState| {
+            let input = state.query_input.as_ref().unwrap();
+            let error = input.lock().unwrap().input_handler_mut().error.clone();
+            error
+        };
+        assert_eq!(error(&state), Some((9, "expected a value".to_string())));
+
+        state.handle_action(AppAction::AcceptQuery("level >= ".to_string()));
+        assert!(state.query_input.is_some());
+        state.handle_action(AppAction::AcceptQuery(
+            "level >= warn and latency_ms > 100".to_string(),
+        ));
+        assert!(state.query_input.is_none());
+        assert_eq!(state.view.len(), 3);
+
+        state.handle_action(AppAction::OpenQuery);
+        state.handle_action(AppAction::AcceptQuery(String::new()));
+        assert_eq!(state.view.len(), 20);
+    }
+
+    #[test]
+    fn test_facet_filters() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 10;
+        state.set_formats(vec![LogFormat::Bracketed], None);
+        for (level, component) in [("ERROR", "db"), ("INFO ", "api"), ("ERROR", "api")] {
+            state.add_line(LogLine::new(0, format!("[{}] [{}] done", level, component)));
+        }
+        state.sync_lines();
+
+        // level, INFO, ERROR, component, api, db
+        state.handle_action(AppAction::OpenFacetPanel);
+        state.handle_action(AppAction::ToggleFacet(2, FilterKind::Include));
+        assert_eq!(state.view.len(), 2);
+        state.handle_action(AppAction::ToggleFacet(5, FilterKind::Exclude));
+        assert_eq!(state.get_lines()[0].1.text, "[ERROR] [api] done");
+        state.handle_action(AppAction::ToggleFacet(3, FilterKind::Include));
+        assert_eq!(state.view.len(), 1);
+
+        state.add_line(LogLine::new(0, "[ERROR] [db] again"));
+        state.sync_lines();
+        assert_eq!(
+            state.facets.rows()[5],
+            FacetRow::Value {
+                field: "component",
+                value: "db",
+                count: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_lines_sampled_early_are_parsed_again() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.add_line(LogLine::new(0, "starting"));
+        state.add_line(LogLine::new(0, "loading config"));
+        state.add_line(LogLine::new(0, r#"{"level":"error","msg":"no config"}"#));
+        assert!(state.lines.get_line(2).unwrap().fields.is_none());
+
+        // Only now is there enough to go on to tell the source is JSON.
+        state.add_line(LogLine::new(
+            0,
+            r#"{"level":"warn","msg":"using defaults"}"#,
+        ));
+        state.sync_lines();
+        let fields = state.lines.get_line(2).unwrap().fields.clone().unwrap();
+        assert_eq!(fields.level, Some(Level::Error));
+        assert_eq!(
+            state.facets.rows()[1..3],
+            [
+                FacetRow::Value {
+                    field: "level",
+                    value: "WARN",
+                    count: 1
+                },
+                FacetRow::Value {
+                    field: "level",
+                    value: "ERROR",
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mapped_lines_counted_again_once_format_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(
+            &path,
+            "level=error component=db msg=a\nlevel=info component=api msg=b\n",
+        )
+        .unwrap();
+        let buffer = LogBuffer::open_mapped(&path, None).unwrap();
+        while buffer.index_progress().is_some() {
+            std::thread::yield_now();
+        }
+        let mut state = AppState::with_buffer(buffer, vec!["app.log".to_string()]);
+        state.last_frame_height = 10;
+        state.set_formats(vec![LogFormat::Logfmt], None);
+
+        // Counted before the format is known, so with no fields.
+        state.count_mapped_lines();
+        assert_eq!(state.facets.counted, 2);
+        assert!(state.facets.rows().is_empty());
+
+        state.handle_action(AppAction::OpenFacetPanel);
+        state.sync_lines();
+        assert_eq!(state.facets.counted, 2);
+        assert_eq!(state.histogram.counted, 2);
+        assert_eq!(
+            state.facets.rows()[1],
+            FacetRow::Value {
+                field: "level",
+                value: "INFO",
+                count: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 4;
+        state.histogram_width = 10;
+        state.set_formats(vec![LogFormat::Logfmt], None);
+        for minute in 0..10 {
+            for _ in 0..=minute {
+                let text = format!("time=2024-01-15T10:{:02}:00 level=info msg=hi", minute);
+                state.add_line(LogLine::new(0, text));
+            }
+            state.add_line(LogLine::new(0, "  continued"));
+        }
+        state.sync_lines();
+
+        state.handle_action(AppAction::OpenHistogram);
+        assert_eq!(state.histogram.buckets.len(), 10);
+        state.handle_action(AppAction::SelectBucket(3));
+        // Minutes 0-2 have 1 + 2 + 3 lines and one continuation line each.
+        assert_eq!(state.cursor, Some(9));
+        assert_eq!(state.line_num, 9);
+
+        // Continuation lines go with the line before them.
+        state.handle_action(AppAction::RestrictToBucket(3));
+        assert_eq!(state.view.len(), 4 + 1);
+        assert_eq!(state.view.buffer_index(4), Some(13));
+        state.handle_action(AppAction::RestrictToBucket(4));
+        assert_eq!(state.view.len(), 5 + 1);
+        state.handle_action(AppAction::RestrictToBucket(4));
+        assert!(!state.filters.is_active());
+        assert_eq!(state.view.len(), 65);
+    }
+
+    #[test]
+    fn test_go_to_time() {
+        let mut state = AppState::new(vec!["stdin".to_string()]);
+        state.last_frame_height = 4;
+        state.set_formats(vec![LogFormat::Logfmt], None);
+        for minute in 0..60 {
+            let text = format!("time=2024-01-15T10:{:02}:00 level=info msg=hi", minute);
+            state.add_line(LogLine::new(0, text));
+        }
+        state.sync_lines();
+
+        assert!(state.accept_go_to_time("2024-01-15 10:20").is_ok());
+        assert_eq!(state.line_num, 20);
+        assert_eq!(state.cursor, Some(20));
+        // Relative times count back from the last line.
+        assert!(state.accept_go_to_time("-15m").is_ok());
+        assert_eq!(state.cursor, Some(44));
+        assert!(state.accept_go_to_time("soon").is_err());
+
+        assert!(state.accept_time_range("later..").is_err());
+        assert!(state.accept_time_range("2024-01-15 10:10..-30m").is_ok());
+        assert_eq!(state.view.len(), 19);
+        assert!(state.accept_time_range("").is_ok());
+        assert!(!state.filters.is_active());
+
+        state.handle_action(AppAction::OpenTimeRange);
+        state.handle_action(AppAction::AcceptTimeRange("10:30..10:20".to_string()));
+        let prompt = state.time_range_input.as_ref().unwrap();
+        let error = prompt.lock().unwrap().input_handler_mut().error.clone();
+        assert_eq!(error.unwrap().1, "the range has to end after it starts");
+        assert!(!state.filters.is_active());
+    }
+
+    #[test]
+    fn test_go_to_line_targets() {
+        let mut state = state_with_lines(1000);
+        let go_to = |state: &mut AppState, input: &str| {
+            state.handle_action(AppAction::AcceptGoToLine(input.to_string()));
+            state.cursor.map(|index| index + 1)
+        };
+
+        assert_eq!(go_to(&mut state, "500"), Some(500));
+        assert_eq!(go_to(&mut state, "+200"), Some(700));
+        assert_eq!(go_to(&mut state, "-50"), Some(650));
+        state.handle_action(AppAction::AwaitMark);
+        state.handle_action(AppAction::SetMark('a'));
+        assert_eq!(go_to(&mut state, "25%"), Some(251));
+        assert_eq!(go_to(&mut state, "$"), Some(1000));
+        assert_eq!(go_to(&mut state, "'a"), Some(650));
+
+        // A target that isn't there leaves the prompt open, with the preview cleared.
+        state.handle_action(AppAction::OpenGoToLine);
+        state.handle_action(AppAction::GoToLineChanged("'b".to_string()));
+        assert_eq!(state.go_to_line_preview, None);
+        state.handle_action(AppAction::AcceptGoToLine("'b".to_string()));
+        assert!(state.go_to_line.is_some());
+        state.handle_action(AppAction::GoToLineChanged("10".to_string()));
+        assert_eq!(state.go_to_line_preview, Some(9));
+    }
+}
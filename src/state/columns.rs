@@ -0,0 +1,137 @@
+use crate::format::Fields;
+
+/// Width given to columns for fields found in the logs, until they're resized.
+const DEFAULT_WIDTH: u16 = 16;
+
+/// Narrowest a column can be made.
+const MIN_WIDTH: u16 = 3;
+
+/// A field shown as a column of the table view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    /// The name the field is looked up by, as in `Fields::get`.
+    pub field: String,
+    /// Width in cells, or `None` to take up whatever space the other columns leave.
+    pub width: Option<u16>,
+    pub visible: bool,
+}
+
+impl Column {
+    fn new(field: &str, width: Option<u16>, visible: bool) -> Self {
+        Self {
+            field: field.to_string(),
+            width,
+            visible,
+        }
+    }
+}
+
+/// The columns of the table view, in order. Every field that has been seen in the logs has a
+/// column, so it can be shown; only the standard fields are shown at first.
+#[derive(Debug, Clone)]
+pub struct Columns {
+    pub columns: Vec<Column>,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                Column::new("timestamp", Some(23), true),
+                Column::new("level", Some(5), true),
+                Column::new("component", Some(12), true),
+                Column::new("message", None, true),
+            ],
+        }
+    }
+}
+
+impl Columns {
+    pub fn visible(&self) -> impl Iterator<Item = &Column> {
+        self.columns.iter().filter(|column| column.visible)
+    }
+
+    /// Add hidden columns for any fields in `fields` that don't have one yet.
+    pub fn discover(&mut self, fields: &Fields) {
+        for (key, _) in &fields.extra {
+            if !self.columns.iter().any(|column| &column.field == key) {
+                self.columns
+                    .push(Column::new(key, Some(DEFAULT_WIDTH), false));
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(column) = self.columns.get_mut(index) {
+            column.visible = !column.visible;
+        }
+    }
+
+    /// Move the column at `index` by `offset` places. Returns where it ended up.
+    pub fn move_by(&mut self, index: usize, offset: isize) -> usize {
+        if index >= self.columns.len() {
+            return index;
+        }
+        let target = index
+            .saturating_add_signed(offset)
+            .min(self.columns.len() - 1);
+        let column = self.columns.remove(index);
+        self.columns.insert(target, column);
+        target
+    }
+
+    /// Make the column at `index` take up the space the other columns leave, sharing it with any
+    /// others that do. Resizing it gives it a fixed width again.
+    pub fn fill(&mut self, index: usize) {
+        if let Some(column) = self.columns.get_mut(index) {
+            column.width = None;
+        }
+    }
+
+    /// Make the column at `index` wider or narrower. A column that fills the leftover space is
+    /// given a fixed width first.
+    pub fn resize(&mut self, index: usize, delta: i16) {
+        if let Some(column) = self.columns.get_mut(index) {
+            let width = column.width.unwrap_or(DEFAULT_WIDTH * 2);
+            column.width = Some(width.saturating_add_signed(delta).max(MIN_WIDTH));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns() {
+        let mut columns = Columns::default();
+        let fields = Fields {
+            extra: vec![
+                ("status".to_string(), "200".to_string()),
+                ("path".to_string(), "/".to_string()),
+            ],
+            ..Default::default()
+        };
+        columns.discover(&fields);
+        columns.discover(&fields);
+        assert_eq!(columns.columns.len(), 6);
+        assert_eq!(columns.visible().count(), 4);
+
+        columns.toggle(4);
+        assert_eq!(columns.move_by(4, -3), 1);
+        assert_eq!(columns.move_by(0, -1), 0);
+        let visible = columns
+            .visible()
+            .map(|column| column.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            visible,
+            ["timestamp", "status", "level", "component", "message"]
+        );
+
+        columns.resize(4, -40);
+        assert_eq!(columns.columns[4].width, Some(MIN_WIDTH));
+        columns.fill(4);
+        assert_eq!(columns.columns[4].width, None);
+    }
+}
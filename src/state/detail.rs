@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+/// Where a node sits in its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKey<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+impl NodeKey<'_> {
+    /// The path of this key under the node at `parent`, in `jq` syntax.
+    fn path_under(self, parent: &str) -> String {
+        match self {
+            Self::Field(key) if is_identifier(key) => format!("{}.{}", parent, key),
+            Self::Field(key) => format!("{}[{}]", parent, Value::from(key)),
+            Self::Index(index) => format!("{}[{}]", parent, index),
+        }
+    }
+}
+
+/// A row of the JSON tree.
+#[derive(Debug)]
+pub struct Node<'a> {
+    pub depth: usize,
+    /// Path from the root to the node, like `.request.headers["user-agent"]`.
+    pub path: String,
+    pub key: NodeKey<'a>,
+    pub value: &'a Value,
+    pub collapsed: bool,
+}
+
+impl Node<'_> {
+    pub fn is_container(&self) -> bool {
+        self.value.is_object() || self.value.is_array()
+    }
+
+    /// The value at this node as it's compared when filtering on it, or `None` for objects and
+    /// arrays. Values are compared once parsed, so `5.50` and `5.5` are the same value.
+    pub fn filter_value(&self) -> Option<String> {
+        (!self.is_container()).then(|| self.value.to_string())
+    }
+}
+
+/// Which parts of the JSON in the detail pane are folded away. Nodes are remembered by path, so
+/// folding a field stays in effect as the cursor moves between lines with the same shape.
+#[derive(Debug, Default)]
+pub struct Detail {
+    collapsed: HashSet<String>,
+    /// The path that was last copied, to confirm it was.
+    pub copied: Option<String>,
+}
+
+impl Detail {
+    /// The rows of the tree for `value`, skipping the children of collapsed nodes. The root
+    /// itself isn't a row.
+    pub fn nodes<'a>(&self, value: &'a Value) -> Vec<Node<'a>> {
+        let mut nodes = Vec::new();
+        self.push_children(value, "", 0, &mut nodes);
+        nodes
+    }
+
+    fn push_children<'a>(
+        &self,
+        value: &'a Value,
+        path: &str,
+        depth: usize,
+        nodes: &mut Vec<Node<'a>>,
+    ) {
+        let children: Vec<(NodeKey, &Value)> = match value {
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, child)| (NodeKey::Field(key), child))
+                .collect(),
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(index, child)| (NodeKey::Index(index), child))
+                .collect(),
+            _ => return,
+        };
+
+        for (key, child) in children {
+            let path = key.path_under(path);
+            let collapsed = self.collapsed.contains(&path);
+            nodes.push(Node {
+                depth,
+                path: path.clone(),
+                key,
+                value: child,
+                collapsed,
+            });
+            if !collapsed {
+                self.push_children(child, &path, depth + 1, nodes);
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, path: &str) {
+        if !self.collapsed.remove(path) {
+            self.collapsed.insert(path.to_string());
+        }
+    }
+}
+
+/// The JSON object in `text`, either the whole of it or embedded in it, like the payload after a
+/// message. Arrays are only looked for if there's no object, since `[INFO]` and the like are
+/// common outside of JSON.
+pub fn find_json(text: &str) -> Option<Value> {
+    ['{', '['].into_iter().find_map(|open| {
+        text.match_indices(open).find_map(|(start, _)| {
+            let mut values = serde_json::Deserializer::from_str(&text[start..]).into_iter();
+            match values.next() {
+                Some(Ok(value @ (Value::Object(_) | Value::Array(_)))) => Some(value),
+                _ => None,
+            }
+        })
+    })
+}
+
+/// The value at `path` under `root`, where `path` is in the `jq` syntax of `Node::path`.
+pub fn value_at<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = root;
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(after.len());
+            value = value.get(&after[..end])?;
+            rest = &after[end..];
+        } else if rest.starts_with("[\"") {
+            // The key is a JSON string, which may have escaped quotes and brackets in it.
+            let mut keys = serde_json::Deserializer::from_str(&rest[1..]).into_iter::<String>();
+            let key = keys.next()?.ok()?;
+            value = value.get(&key)?;
+            rest = rest[1 + keys.byte_offset()..].strip_prefix(']')?;
+        } else {
+            let after = rest.strip_prefix('[')?;
+            let end = after.find(']')?;
+            value = value.get(after[..end].parse::<usize>().ok()?)?;
+            rest = &after[end + 1..];
+        }
+    }
+    Some(value)
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_json() {
+        let line = r#"12:00:01 [INFO] request done {"status": 500, "req": {"user-agent": "curl", "ids": [4, 5]}} trailing"#;
+        let value = find_json(line).unwrap();
+        assert_eq!(value["status"], 500);
+        assert!(find_json("[INFO] no json {here").is_none());
+        assert_eq!(find_json("ids [1, 2]"), Some(Value::from(vec![1, 2])));
+
+        let mut detail = Detail::default();
+        let paths = |detail: &Detail| {
+            detail
+                .nodes(&value)
+                .iter()
+                .map(|node| (node.depth, node.path.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(&detail),
+            [
+                (0, ".status".to_string()),
+                (0, ".req".to_string()),
+                (1, ".req[\"user-agent\"]".to_string()),
+                (1, ".req.ids".to_string()),
+                (2, ".req.ids[0]".to_string()),
+                (2, ".req.ids[1]".to_string()),
+            ]
+        );
+
+        detail.toggle(".req");
+        assert_eq!(paths(&detail).len(), 2);
+        assert!(detail.nodes(&value)[1].collapsed);
+        detail.toggle(".req");
+        assert_eq!(paths(&detail).len(), 6);
+
+        let nodes = detail.nodes(&value);
+        for node in &nodes {
+            assert_eq!(value_at(&value, &node.path), Some(node.value));
+        }
+        assert_eq!(nodes[2].filter_value().as_deref(), Some("\"curl\""));
+        assert!(nodes[1].filter_value().is_none());
+        assert!(value_at(&value, ".req.missing").is_none());
+        assert!(value_at(&value, ".status.x").is_none());
+        let escaped = serde_json::json!({"a\"]b": {"c": [true]}});
+        assert_eq!(
+            value_at(&escaped, r#"["a\"]b"].c[0]"#),
+            Some(&Value::Bool(true))
+        );
+    }
+}
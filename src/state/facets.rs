@@ -0,0 +1,147 @@
+use crate::format::{Fields, Level};
+
+/// Fields with more distinct values than this aren't facets, like IDs and durations, and stop
+/// being counted.
+const MAX_VALUES: usize = 30;
+
+/// The distinct values a field has taken, with how many lines had each.
+#[derive(Debug)]
+struct Facet {
+    field: String,
+    values: Vec<(String, usize)>,
+    too_many: bool,
+}
+
+/// A row of the facet sidebar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FacetRow<'a> {
+    Field(&'a str),
+    Value {
+        field: &'a str,
+        value: &'a str,
+        count: usize,
+    },
+}
+
+/// Counts of the values of each low-cardinality field in the buffer, like how many lines there
+/// are of each level and from each component.
+#[derive(Debug, Default)]
+pub struct Facets {
+    facets: Vec<Facet>,
+    /// Number of buffer lines that have been counted.
+    pub counted: usize,
+}
+
+impl Facets {
+    /// Count the next line of the buffer, which has `fields` if it could be parsed.
+    pub fn count(&mut self, fields: Option<&Fields>) {
+        self.counted += 1;
+        let Some(fields) = fields else {
+            return;
+        };
+        if let Some(level) = fields.level {
+            self.add("level", level.name());
+        }
+        if let Some(component) = fields.component.as_deref() {
+            self.add("component", component);
+        }
+        for (key, value) in &fields.extra {
+            self.add(key, value);
+        }
+    }
+
+    fn add(&mut self, field: &str, value: &str) {
+        let index = match self.facets.iter().position(|facet| facet.field == field) {
+            Some(index) => index,
+            None => {
+                self.facets.push(Facet {
+                    field: field.to_string(),
+                    values: Vec::new(),
+                    too_many: false,
+                });
+                self.facets.len() - 1
+            }
+        };
+
+        let facet = &mut self.facets[index];
+        if facet.too_many {
+            return;
+        }
+        match facet.values.iter().position(|(v, _)| v == value) {
+            Some(index) => facet.values[index].1 += 1,
+            None if facet.values.len() >= MAX_VALUES => {
+                facet.too_many = true;
+                facet.values = Vec::new();
+            }
+            None => facet.values.push((value.to_string(), 1)),
+        }
+    }
+
+    /// Each facet followed by its values, which are in order so they don't move around as they
+    /// are counted: levels by severity and anything else alphabetically.
+    pub fn rows(&self) -> Vec<FacetRow<'_>> {
+        let mut rows = Vec::new();
+        for facet in self.facets.iter().filter(|facet| !facet.too_many) {
+            let mut values = facet.values.iter().collect::<Vec<_>>();
+            if facet.field == "level" {
+                values.sort_by_key(|(value, _)| Level::parse(value));
+            } else {
+                values.sort();
+            }
+
+            rows.push(FacetRow::Field(&facet.field));
+            rows.extend(values.into_iter().map(|(value, count)| FacetRow::Value {
+                field: &facet.field,
+                value,
+                count: *count,
+            }));
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::LogFormat;
+
+    #[test]
+    fn test_counts() {
+        let mut facets = Facets::default();
+        for i in 0..40 {
+            let level = ["error", "info", "warn"][i % 3];
+            let line = format!("level={} component=db request_id={}", level, i);
+            facets.count(LogFormat::Logfmt.parse(&line).as_ref());
+        }
+        facets.count(None);
+        assert_eq!(facets.counted, 41);
+
+        assert_eq!(
+            facets.rows(),
+            [
+                FacetRow::Field("level"),
+                FacetRow::Value {
+                    field: "level",
+                    value: "INFO",
+                    count: 13
+                },
+                FacetRow::Value {
+                    field: "level",
+                    value: "WARN",
+                    count: 13
+                },
+                FacetRow::Value {
+                    field: "level",
+                    value: "ERROR",
+                    count: 14
+                },
+                FacetRow::Field("component"),
+                FacetRow::Value {
+                    field: "component",
+                    value: "db",
+                    count: 40
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::{borrow::Cow, cell::OnceCell, ops::Range};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    format::Level,
+    state::{
+        detail::{find_json, value_at},
+        line_buffer::{LineContext, LogLine},
+        pattern::Pattern,
+        query::Query,
+    },
+    ui::display_line::display_text,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    /// Only show lines that match.
+    Include,
+    /// Hide lines that match.
+    Exclude,
+}
+
+/// One step of a `FilterChain`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub pattern: Pattern,
+    pub enabled: bool,
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind, pattern: Pattern) -> Self {
+        Self {
+            kind,
+            pattern,
+            enabled: true,
+        }
+    }
+}
+
+/// A value of a field picked from the facet sidebar, like `component=db`, or of a JSON value
+/// picked in the detail pane, like `.req.status=500`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldFilter {
+    pub kind: FilterKind,
+    /// A field name, or the path of a value in the line's JSON, which starts with `.`.
+    pub field: String,
+    /// The field's value, or the JSON value serialized the way `serde_json` does.
+    pub value: String,
+}
+
+/// An ordered list of filters that a line has to get through every one of to be shown, like a
+/// pipeline of `grep` and `grep -v`, along with a minimum level. The lines that pass are tracked
+/// by the app's `View`, which only has to check lines appended since it was last updated.
+#[derive(Debug, Default)]
+pub struct FilterChain {
+    pub filters: Vec<Filter>,
+    /// Hide lines with a level below this one. Lines that couldn't be parsed go by the level of
+    /// the line they continue, so a stack trace is shown or hidden along with its error.
+    pub min_level: Option<Level>,
+    /// Only show lines whose fields match this query.
+    pub query: Option<Query>,
+    /// Lines have to have one of the included values of each field with any, and none of the
+    /// excluded ones.
+    pub field_filters: Vec<FieldFilter>,
+    /// Only show lines with a timestamp in this range, going by the line they continue for lines
+    /// that couldn't be parsed, like `min_level`. Lines with no timestamp at all are shown.
+    pub time_range: Option<Range<NaiveDateTime>>,
+}
+
+impl FilterChain {
+    /// Include `field=value` if it isn't already, switch it between including and excluding if
+    /// it's already filtered on, or stop filtering on it if it's already included or excluded as
+    /// `kind` asks.
+    pub fn toggle_field_filter(&mut self, kind: FilterKind, field: &str, value: &str) {
+        let existing = self
+            .field_filters
+            .iter()
+            .position(|filter| filter.field == field && filter.value == value);
+        match existing {
+            Some(index) if self.field_filters[index].kind == kind => {
+                self.field_filters.remove(index);
+            }
+            Some(index) => self.field_filters[index].kind = kind,
+            None => self.field_filters.push(FieldFilter {
+                kind,
+                field: field.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    fn accepts_fields(&self, line: &LogLine) -> bool {
+        // The JSON in the line is only looked for once, and only if a filter is on a path in it.
+        let json = OnceCell::new();
+        let value = |field: &str| {
+            if field.starts_with('.') {
+                let json = json.get_or_init(|| find_json(&line.text));
+                return value_at(json.as_ref()?, field).map(|value| Cow::Owned(value.to_string()));
+            }
+            line.fields.as_ref().and_then(|fields| fields.get(field))
+        };
+        let mut included_fields = self
+            .field_filters
+            .iter()
+            .filter(|filter| filter.kind == FilterKind::Include)
+            .map(|filter| filter.field.as_str());
+        let excluded = self.field_filters.iter().any(|filter| {
+            filter.kind == FilterKind::Exclude
+                && value(&filter.field).is_some_and(|value| value == filter.value)
+        });
+
+        !excluded
+            && included_fields.all(|field| {
+                let value = value(field);
+                self.field_filters.iter().any(|filter| {
+                    filter.kind == FilterKind::Include
+                        && filter.field == field
+                        && value.as_deref() == Some(filter.value.as_str())
+                })
+            })
+    }
+
+    /// Whether any filter is enabled, i.e. whether any line could be hidden.
+    pub fn is_active(&self) -> bool {
+        self.min_level.is_some()
+            || self.query.is_some()
+            || !self.field_filters.is_empty()
+            || self.time_range.is_some()
+            || self.filters.iter().any(|filter| filter.enabled)
+    }
+
+    /// Whether `line`, with the level and timestamp in `context`, gets through every filter.
+    /// Patterns are matched against the text as it's displayed with `tab_width`, like searches.
+    pub fn accepts(&self, line: &LogLine, context: LineContext, tab_width: usize) -> bool {
+        if let (Some(min_level), Some(level)) = (self.min_level, context.level) {
+            if level < min_level {
+                return false;
+            }
+        }
+
+        if let (Some(range), Some(timestamp)) = (self.time_range.as_ref(), context.timestamp) {
+            if !range.contains(&timestamp) {
+                return false;
+            }
+        }
+
+        if let Some(query) = self.query.as_ref() {
+            if !query.matches(line.fields.as_deref()) {
+                return false;
+            }
+        }
+        if !self.field_filters.is_empty() && !self.accepts_fields(line) {
+            return false;
+        }
+        let mut text = None;
+        self.filters
+            .iter()
+            .filter(|filter| filter.enabled)
+            .all(|filter| {
+                let text = text.get_or_insert_with(|| display_text(&line.text, tab_width));
+                filter.pattern.is_match(text) == (filter.kind == FilterKind::Include)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        format::LogFormat,
+        state::{line_buffer::LineContexts, pattern::PatternOptions},
+    };
+
+    fn filter(kind: FilterKind, pattern: &str) -> Filter {
+        Filter::new(
+            kind,
+            Pattern::new(pattern.to_string(), PatternOptions::default()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut chain = FilterChain::default();
+        assert!(!chain.is_active());
+
+        chain.filters.push(filter(FilterKind::Include, "auth"));
+        chain
+            .filters
+            .push(filter(FilterKind::Exclude, "healthcheck"));
+        assert!(chain.is_active());
+
+        let accepted = |chain: &FilterChain, text| {
+            let line = LogLine::new(0, text);
+            chain.accepts(&line, LineContext::default(), 8)
+        };
+        assert!(accepted(&chain, "[auth] ERROR bad password"));
+        assert!(!accepted(&chain, "[api] ERROR timeout"));
+        assert!(!accepted(&chain, "[auth] GET /healthcheck"));
+
+        chain.filters[1].enabled = false;
+        assert!(accepted(&chain, "[auth] GET /healthcheck"));
+
+        chain.filters[0].enabled = false;
+        assert!(!chain.is_active());
+        assert!(accepted(&chain, "[api] ERROR timeout"));
+    }
+
+    #[test]
+    fn test_field_filters() {
+        let mut chain = FilterChain::default();
+        chain.toggle_field_filter(FilterKind::Include, "component", "db");
+        chain.toggle_field_filter(FilterKind::Include, "component", "api");
+        chain.toggle_field_filter(FilterKind::Include, "level", "ERROR");
+        chain.toggle_field_filter(FilterKind::Include, "level", "WARN");
+        chain.toggle_field_filter(FilterKind::Exclude, "level", "WARN");
+        assert!(chain.is_active());
+        let accepted = |chain: &FilterChain, text: &str| {
+            let mut line = LogLine::new(0, text);
+            line.parse(Some(&LogFormat::Bracketed));
+            chain.accepts(&line, LineContext::default(), 8)
+        };
+        assert!(accepted(&chain, "[ERROR] [db] connection lost"));
+        assert!(accepted(&chain, "[ERROR] [api] timeout"));
+        assert!(!accepted(&chain, "[ERROR] [auth] bad password"));
+        assert!(!accepted(&chain, "[WARN ] [db] slow query"));
+        assert!(!accepted(&chain, "not parsed"));
+
+        chain.toggle_field_filter(FilterKind::Include, "level", "ERROR");
+        chain.toggle_field_filter(FilterKind::Include, "component", "db");
+        chain.toggle_field_filter(FilterKind::Include, "component", "api");
+        assert!(accepted(&chain, "[INFO ] [auth] login"));
+        assert!(!accepted(&chain, "[WARN ] [auth] slow"));
+        assert!(accepted(&chain, "not parsed"));
+    }
+
+    #[test]
+    fn test_min_level() {
+        let chain = FilterChain {
+            min_level: Some(Level::Warn),
+            ..Default::default()
+        };
+        let mut contexts = LineContexts::default();
+        let mut accepted = |source: usize, text: &str| {
+            let mut line = LogLine::new(source, text);
+            line.parse(Some(&LogFormat::Bracketed));
+            chain.accepts(&line, contexts.next(&line), 8)
+        };
+        assert!(accepted(0, "    at the start"));
+        assert!(accepted(0, "[ERROR] [db] connection lost"));
+        assert!(accepted(0, "    at Connection.query"));
+        assert!(accepted(0, "[WARN ] [api] slow"));
+        assert!(!accepted(0, "[INFO ] [api] started"));
+        assert!(!accepted(0, "    at Server.listen"));
+        assert!(accepted(1, "    at Connection.query"));
+    }
+}
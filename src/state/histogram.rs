@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+
+use crate::state::line_buffer::{LineContexts, LogLine};
+
+/// Lines are counted by level, with lines that have no level in the last slot.
+pub const SLOTS: usize = 7;
+
+/// Bucket sizes in seconds, from which the smallest that fits the whole log on screen is used.
+/// Logs spanning more than a week use some number of weeks.
+const BUCKET_SIZES: [i64; 20] = [
+    1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600, 7200, 10800, 21600, 43200, 86400,
+    172800, 604800,
+];
+
+const WEEK: i64 = 604800;
+
+/// The lines counted in one second.
+#[derive(Debug, Clone, Copy)]
+struct Second {
+    first_index: usize,
+    counts: [usize; SLOTS],
+}
+
+/// A bar of the histogram: the lines in `start..end`, by level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bucket {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub counts: [usize; SLOTS],
+    /// Buffer index of the first line in the bucket, if there are any.
+    pub first_index: Option<usize>,
+}
+
+impl Bucket {
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// How many lines were logged over time. Lines are counted at their timestamp and level, or
+/// those of the line they continue if they couldn't be parsed, like the rest of a stack trace.
+/// Lines before the first timestamp, like a banner at the top of the log, are counted with the
+/// first line that has one. Until a line does, they're counted at the time they were read.
+#[derive(Debug, Default)]
+pub struct Histogram {
+    seconds: BTreeMap<i64, Second>,
+    /// Number of buffer lines that have been counted.
+    pub counted: usize,
+    contexts: LineContexts,
+    last_time: Option<NaiveDateTime>,
+    /// The bars last built with `update`.
+    pub buckets: Vec<Bucket>,
+    /// Length of a bar in seconds.
+    pub bucket_size: i64,
+    /// The bar count and counted lines `buckets` were built for.
+    built: Option<(usize, usize)>,
+}
+
+impl Histogram {
+    /// Count the next line of the buffer.
+    pub fn count(&mut self, line: &LogLine) {
+        let context = self.contexts.next(line);
+        if let Some(timestamp) = context.timestamp {
+            if self.last_time.is_none() {
+                self.move_undated(timestamp);
+            }
+            self.last_time = Some(timestamp);
+        }
+        let slot = context.level.map_or(SLOTS - 1, |level| level as usize);
+        self.add(self.last_time, slot, 1);
+    }
+
+    /// Count the next `lines` lines of the buffer, none of which could be parsed.
+    pub fn count_unparsed(&mut self, lines: usize) {
+        if lines > 0 {
+            self.add(self.last_time, SLOTS - 1, lines);
+        }
+    }
+
+    fn add(&mut self, time: Option<NaiveDateTime>, slot: usize, lines: usize) {
+        let time = time.unwrap_or_else(|| Local::now().naive_local());
+        let second = self
+            .seconds
+            .entry(time.and_utc().timestamp())
+            .or_insert(Second {
+                first_index: self.counted,
+                counts: [0; SLOTS],
+            });
+        second.counts[slot] += lines;
+        self.counted += lines;
+    }
+
+    /// Move the lines counted so far, none of which had a timestamp, from when they were read to
+    /// `time`, so they don't stretch the histogram from when the log was written until now.
+    fn move_undated(&mut self, time: NaiveDateTime) {
+        let undated =
+            std::mem::take(&mut self.seconds)
+                .into_values()
+                .reduce(|mut total, second| {
+                    for (total, count) in total.counts.iter_mut().zip(second.counts) {
+                        *total += count;
+                    }
+                    total.first_index = total.first_index.min(second.first_index);
+                    total
+                });
+        if let Some(undated) = undated {
+            self.seconds.insert(time.and_utc().timestamp(), undated);
+        }
+    }
+
+    /// Whether lines are counted at their timestamps, rather than when they were read.
+    pub fn is_timed(&self) -> bool {
+        self.last_time.is_some()
+    }
+
+    /// Rebuild `buckets` with at most `bars` bars, if anything has changed since they were last
+    /// built. Buckets start at whole multiples of their size, so they stay put as lines come in.
+    pub fn update(&mut self, bars: usize) {
+        let bars = bars.max(1);
+        if self.built == Some((bars, self.counted)) {
+            return;
+        }
+        self.built = Some((bars, self.counted));
+
+        let (Some(&first), Some(&last)) = (self.seconds.keys().next(), self.seconds.keys().last())
+        else {
+            self.buckets = Vec::new();
+            return;
+        };
+        let size = BUCKET_SIZES
+            .into_iter()
+            .chain((2..).map(|weeks| weeks * WEEK))
+            .find(|&size| last.div_euclid(size) - first.div_euclid(size) < bars as i64)
+            .unwrap_or(WEEK);
+        let start = first.div_euclid(size);
+        let time = |bucket: i64| {
+            DateTime::from_timestamp(bucket * size, 0)
+                .map(|time| time.naive_utc())
+                .unwrap_or_default()
+        };
+
+        self.bucket_size = size;
+        self.buckets = (start..=last.div_euclid(size))
+            .map(|bucket| Bucket {
+                start: time(bucket),
+                end: time(bucket + 1),
+                counts: [0; SLOTS],
+                first_index: None,
+            })
+            .collect();
+        for (&second, counts) in &self.seconds {
+            let bucket = &mut self.buckets[(second.div_euclid(size) - start) as usize];
+            for (total, count) in bucket.counts.iter_mut().zip(counts.counts) {
+                *total += count;
+            }
+            bucket.first_index = Some(
+                bucket
+                    .first_index
+                    .map_or(counts.first_index, |index| index.min(counts.first_index)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{Level, LogFormat};
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn line(text: &str) -> LogLine {
+        let mut line = LogLine::new(0, text);
+        line.parse(Some(&LogFormat::Logfmt));
+        line
+    }
+
+    #[test]
+    fn test_buckets() {
+        let mut histogram = Histogram::default();
+        for (i, second) in [0, 10, 15, 59, 65, 185].into_iter().enumerate() {
+            let level = ["info", "error"][i % 2];
+            let text = format!(
+                "time=2024-01-15T10:{:02}:{:02} level={}",
+                second / 60,
+                second % 60,
+                level
+            );
+            histogram.count(&line(&text));
+        }
+        // A continuation line is counted with the line before.
+        histogram.count(&line("  continued"));
+        assert_eq!(histogram.counted, 7);
+        assert!(histogram.is_timed());
+
+        histogram.update(4);
+        assert_eq!(histogram.bucket_size, 60);
+        let buckets = &histogram.buckets;
+        assert_eq!(buckets.len(), 4);
+        assert_eq!(buckets[0].start, time("2024-01-15 10:00:00"));
+        assert_eq!(buckets[0].end, time("2024-01-15 10:01:00"));
+        assert_eq!(buckets[0].total(), 4);
+        assert_eq!(buckets[0].counts[Level::Error as usize], 2);
+        assert_eq!(buckets[1].first_index, Some(4));
+        assert_eq!(buckets[2].total(), 0);
+        assert_eq!(buckets[2].first_index, None);
+        assert_eq!(buckets[3].counts, [0, 0, 0, 0, 2, 0, 0]);
+
+        histogram.update(200);
+        assert_eq!(histogram.bucket_size, 1);
+        assert_eq!(histogram.buckets.len(), 186);
+    }
+
+    #[test]
+    fn test_undated_header() {
+        let mut histogram = Histogram::default();
+        histogram.count(&line("=== app v1.2 starting ==="));
+        histogram.count(&line("  config loaded"));
+        assert!(!histogram.is_timed());
+        histogram.update(10);
+        assert_eq!(histogram.buckets.len(), 1);
+
+        for minute in [0, 5, 9] {
+            let text = format!("time=2024-01-15T10:{:02}:00 level=info", minute);
+            histogram.count(&line(&text));
+        }
+        histogram.update(10);
+        assert_eq!(histogram.bucket_size, 60);
+        let buckets = &histogram.buckets;
+        assert_eq!(buckets.len(), 10);
+        assert_eq!(buckets[0].start, time("2024-01-15 10:00:00"));
+        assert_eq!(buckets[0].counts, [0, 0, 1, 0, 0, 0, 2]);
+        assert_eq!(buckets[0].first_index, Some(0));
+        assert_eq!(buckets[9].total(), 1);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Entries kept for each prompt. Older ones are dropped when the file is next loaded.
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryKind {
+    Search,
+    Filter,
+    GoToLine,
+    Query,
+    GoToTime,
+    TimeRange,
+}
+
+impl HistoryKind {
+    const ALL: [HistoryKind; 6] = [
+        Self::Search,
+        Self::Filter,
+        Self::GoToLine,
+        Self::Query,
+        Self::GoToTime,
+        Self::TimeRange,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Search => "search",
+            Self::Filter => "filter",
+            Self::GoToLine => "goto",
+            Self::Query => "query",
+            Self::GoToTime => "time",
+            Self::TimeRange => "range",
+        }
+    }
+}
+
+/// What was entered into the search, filter, go-to-line, query, go-to-time and time range prompts,
+/// oldest first.
+///
+/// The history is saved to a file shared by every running `cant`. Entries are appended to it as
+/// they are made, one per line as `<kind>\t<entry>`, so sessions don't overwrite each other's, and
+/// the file is compacted when it's loaded. Failing to read or write the file isn't worth
+/// interrupting anyone over, so the history just isn't saved then.
+#[derive(Debug, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    search: Vec<String>,
+    filter: Vec<String>,
+    go_to_line: Vec<String>,
+    query: Vec<String>,
+    go_to_time: Vec<String>,
+    time_range: Vec<String>,
+}
+
+impl History {
+    /// `$XDG_STATE_HOME/cant/history`, falling back to `~/.local/state/cant/history`.
+    pub fn default_path() -> Option<PathBuf> {
+        let state_home = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".local/state")))?;
+        Some(state_home.join("cant").join("history"))
+    }
+
+    /// Load the history saved at `path`, which new entries are then saved to.
+    pub fn load(path: PathBuf) -> Self {
+        let mut history = Self {
+            path: Some(path),
+            ..Default::default()
+        };
+        let Some(contents) = history
+            .path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+        else {
+            return history;
+        };
+
+        let mut saved = 0;
+        for line in contents.lines() {
+            let Some((name, entry)) = line.split_once('\t') else {
+                continue;
+            };
+            if let Some(kind) = HistoryKind::ALL.into_iter().find(|k| k.name() == name) {
+                push(history.entries_mut(kind), entry);
+                saved += 1;
+            }
+        }
+
+        let kept = HistoryKind::ALL
+            .iter()
+            .map(|&kind| history.entries(kind).len())
+            .sum::<usize>();
+        if saved > kept * 2 {
+            let _ = history.compact();
+        }
+        history
+    }
+
+    pub fn entries(&self, kind: HistoryKind) -> &[String] {
+        match kind {
+            HistoryKind::Search => &self.search,
+            HistoryKind::Filter => &self.filter,
+            HistoryKind::GoToLine => &self.go_to_line,
+            HistoryKind::Query => &self.query,
+            HistoryKind::GoToTime => &self.go_to_time,
+            HistoryKind::TimeRange => &self.time_range,
+        }
+    }
+
+    fn entries_mut(&mut self, kind: HistoryKind) -> &mut Vec<String> {
+        match kind {
+            HistoryKind::Search => &mut self.search,
+            HistoryKind::Filter => &mut self.filter,
+            HistoryKind::GoToLine => &mut self.go_to_line,
+            HistoryKind::Query => &mut self.query,
+            HistoryKind::GoToTime => &mut self.go_to_time,
+            HistoryKind::TimeRange => &mut self.time_range,
+        }
+    }
+
+    /// Add an entry, moving it to the end if it's already there.
+    pub fn add(&mut self, kind: HistoryKind, entry: &str) {
+        if entry.is_empty() || entry.contains(['\n', '\r']) {
+            return;
+        }
+        push(self.entries_mut(kind), entry);
+
+        if let Some(path) = self.path.as_ref() {
+            let _ = append(path, &format!("{}\t{}\n", kind.name(), entry));
+        }
+    }
+
+    /// Rewrite the file with only the entries that are kept.
+    fn compact(&self) -> io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut contents = String::new();
+        for kind in HistoryKind::ALL {
+            for entry in self.entries(kind) {
+                contents.push_str(&format!("{}\t{}\n", kind.name(), entry));
+            }
+        }
+
+        // Write to the side and rename, so another session never sees a half-written file.
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp, contents)?;
+        fs::rename(temp, path)
+    }
+}
+
+fn push(entries: &mut Vec<String>, entry: &str) {
+    entries.retain(|e| e != entry);
+    entries.push(entry.to_string());
+    if entries.len() > MAX_ENTRIES {
+        entries.remove(0);
+    }
+}
+
+fn append(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_between_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cant").join("history");
+
+        let mut first = History::load(path.clone());
+        let mut second = History::load(path.clone());
+        first.add(HistoryKind::Search, "error");
+        second.add(HistoryKind::Filter, "api");
+        second.add(HistoryKind::GoToLine, "120");
+        first.add(HistoryKind::Search, "timeout");
+        first.add(HistoryKind::Search, "error");
+        first.add(HistoryKind::Search, "");
+
+        let loaded = History::load(path);
+        assert_eq!(loaded.entries(HistoryKind::Search), ["timeout", "error"]);
+        assert_eq!(loaded.entries(HistoryKind::Filter), ["api"]);
+        assert_eq!(loaded.entries(HistoryKind::GoToLine), ["120"]);
+    }
+}
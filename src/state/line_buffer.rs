@@ -1,30 +1,245 @@
-use std::cmp::min;
+mod chunked;
+mod mapped;
 
-pub struct LogBuffer {
-    pub lines: Vec<String>,
+use std::{borrow::Cow, io, path::Path};
+
+use chrono::NaiveDateTime;
+use chunked::ChunkedLines;
+use mapped::MappedFile;
+
+use crate::{
+    encoding::Encoding,
+    format::{Fields, Level, LogFormat},
+};
+
+/// Lines without a timestamp looked past for one with, when finding lines by time.
+const TIMESTAMP_SEARCH_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    /// Index of the input source the line was read from.
+    pub source: usize,
+    pub text: String,
+    /// The fields parsed out of the line, if its source is in a known format.
+    pub fields: Option<Box<Fields>>,
+}
+
+impl LogLine {
+    pub fn new(source: usize, text: impl Into<String>) -> Self {
+        Self {
+            source,
+            text: text.into(),
+            fields: None,
+        }
+    }
+
+    /// Parse the line's fields in the given format.
+    pub fn parse(&mut self, format: Option<&LogFormat>) {
+        self.fields = format
+            .and_then(|format| format.parse(&self.text))
+            .map(Box::new);
+    }
+
+    fn mapped(text: impl Into<String>, format: Option<&LogFormat>) -> Self {
+        let mut line = Self::new(0, text);
+        line.parse(format);
+        line
+    }
+}
+
+/// The level and timestamp a line is filtered and counted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LineContext {
+    pub level: Option<Level>,
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+/// Works out the `LineContext` of each line, going through the buffer in order. A line that
+/// couldn't be parsed is usually a continuation of the one before, like the rest of a stack
+/// trace, so it shares the context of the last parsed line from the same source.
+#[derive(Debug, Default)]
+pub struct LineContexts {
+    /// The context of the last parsed line from each source.
+    last: Vec<LineContext>,
+}
+
+impl LineContexts {
+    /// The context of the next line of the buffer.
+    pub fn next(&mut self, line: &LogLine) -> LineContext {
+        if self.last.len() <= line.source {
+            self.last.resize(line.source + 1, LineContext::default());
+        }
+        if let Some(fields) = line.fields.as_ref() {
+            self.last[line.source] = LineContext {
+                level: fields.level,
+                timestamp: fields.timestamp,
+            };
+        }
+        self.last[line.source]
+    }
+}
+
+pub enum LogBuffer {
+    /// Lines streamed in from readers and held in memory, already parsed.
+    Memory(ChunkedLines),
+    /// Lines read on demand from a memory-mapped file, which is always source `0`, and parsed
+    /// in the given format as they are read.
+    Mapped(MappedFile, Option<LogFormat>),
 }
 
 impl LogBuffer {
     pub fn new() -> Self {
-        Self { lines: Vec::new() }
+        Self::Memory(ChunkedLines::default())
     }
 
-    pub fn add_line(&mut self, line: String) {
-        self.lines.push(line);
+    pub fn open_mapped(path: &Path, encoding: Option<Encoding>) -> io::Result<Self> {
+        Ok(Self::Mapped(MappedFile::open(path, encoding)?, None))
     }
 
-    pub fn get_lines(&self, start: u16, count: u16) -> Vec<String> {
-        if self.lines.is_empty() {
-            return Vec::new();
+    pub fn add_line(&mut self, line: LogLine) {
+        match self {
+            Self::Memory(lines) => lines.push(line),
+            Self::Mapped(..) => unreachable!("lines cannot be added to a memory-mapped buffer"),
         }
+    }
 
-        let start = start as usize;
-        let end = min(self.lines.len(), start + count as usize);
-        self.lines[start..end].to_vec()
+    /// Parse the lines at `indices` again in `format`, e.g. once the format of their source has
+    /// been worked out. Lines of a memory-mapped buffer are parsed when they are read instead.
+    pub fn reparse(&mut self, indices: &[usize], format: Option<&LogFormat>) {
+        if let Self::Memory(lines) = self {
+            for &index in indices {
+                if let Some(line) = lines.get_mut(index) {
+                    line.parse(format);
+                }
+            }
+        }
+    }
+
+    /// Set the format lines of a memory-mapped buffer are parsed in. Lines held in memory are
+    /// parsed as they are added instead.
+    pub fn set_format(&mut self, format: Option<LogFormat>) {
+        if let Self::Mapped(_, mapped_format) = self {
+            *mapped_format = format;
+        }
+    }
+
+    pub fn get_lines(&self, start: u64, count: u16) -> Vec<LogLine> {
+        match self {
+            Self::Memory(lines) => lines
+                .iter_from(start.try_into().unwrap_or(usize::MAX))
+                .take(count as usize)
+                .cloned()
+                .collect(),
+            Self::Mapped(file, format) => file
+                .get_lines(start as usize, count as usize)
+                .into_iter()
+                .map(|text| LogLine::mapped(text, format.as_ref()))
+                .collect(),
+        }
+    }
+
+    pub fn get_line(&self, index: usize) -> Option<Cow<'_, LogLine>> {
+        match self {
+            Self::Memory(lines) => lines.get(index).map(Cow::Borrowed),
+            Self::Mapped(file, format) => file
+                .get_lines(index, 1)
+                .pop()
+                .map(|text| Cow::Owned(LogLine::mapped(text, format.as_ref()))),
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.lines.len()
+        match self {
+            Self::Memory(lines) => lines.len(),
+            Self::Mapped(file, _) => file.len(),
+        }
+    }
+
+    /// Iterate over the lines starting at the zero-based index `start`.
+    pub fn iter_from(&self, start: usize) -> Box<dyn Iterator<Item = Cow<'_, LogLine>> + '_> {
+        match self {
+            Self::Memory(lines) => Box::new(lines.iter_from(start).map(Cow::Borrowed)),
+            Self::Mapped(file, format) => Box::new(
+                file.iter_from(start)
+                    .map(|text| Cow::Owned(LogLine::mapped(text, format.as_ref()))),
+            ),
+        }
+    }
+
+    /// Iterate over the text of the lines starting at `start`, without parsing them.
+    pub fn texts_from(&self, start: usize) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        match self {
+            Self::Memory(lines) => Box::new(
+                lines
+                    .iter_from(start)
+                    .map(|line| Cow::Borrowed(line.text.as_str())),
+            ),
+            Self::Mapped(file, _) => Box::new(file.iter_from(start)),
+        }
+    }
+
+    /// The line logged closest to `time`, found by binary searching the timestamps of the lines,
+    /// which are assumed to be in order.
+    pub fn find_time(&self, time: NaiveDateTime) -> Option<usize> {
+        let (mut low, mut high) = (0, self.len());
+        let mut after = None;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self.timestamp_from(middle) {
+                Some((index, timestamp)) if timestamp < time => low = index + 1,
+                found => {
+                    after = found.or(after);
+                    high = middle;
+                }
+            }
+        }
+
+        let before = match (self.timestamp_before(low), after) {
+            (Some((_, before)), Some((_, after))) if time - before < after - time => before,
+            (Some((_, before)), None) => before,
+            (_, Some((index, _))) => return Some(index),
+            (None, None) => return None,
+        };
+        // Land on the first of the lines logged at that time.
+        self.find_time(before)
+    }
+
+    /// The timestamp of the last line that has one.
+    pub fn last_timestamp(&self) -> Option<NaiveDateTime> {
+        self.timestamp_before(self.len())
+            .map(|(_, timestamp)| timestamp)
+    }
+
+    /// The first line with a timestamp from `start` on, with its index.
+    fn timestamp_from(&self, start: usize) -> Option<(usize, NaiveDateTime)> {
+        (start..)
+            .zip(self.iter_from(start).take(TIMESTAMP_SEARCH_LIMIT))
+            .find_map(|(index, line)| Some((index, line.fields.as_ref()?.timestamp?)))
+    }
+
+    /// The last line with a timestamp before `end`, with its index.
+    fn timestamp_before(&self, end: usize) -> Option<(usize, NaiveDateTime)> {
+        (end.saturating_sub(TIMESTAMP_SEARCH_LIMIT)..end)
+            .rev()
+            .find_map(|index| Some((index, self.get_line(index)?.fields.as_ref()?.timestamp?)))
+    }
+
+    /// A copy of the buffer as it is now that can be sent to another thread. Lines are shared
+    /// with the original rather than copied where possible.
+    pub fn snapshot(&self) -> Self {
+        match self {
+            Self::Memory(lines) => Self::Memory(lines.snapshot()),
+            Self::Mapped(file, format) => Self::Mapped(file.clone(), format.clone()),
+        }
+    }
+
+    /// Fraction of the buffer's source that has been split into lines, or `None` if every line
+    /// is already available.
+    pub fn index_progress(&self) -> Option<f64> {
+        match self {
+            Self::Memory(_) => None,
+            Self::Mapped(file, _) => file.index_progress(),
+        }
     }
 }
 
@@ -32,45 +247,111 @@ impl LogBuffer {
 mod tests {
     use super::*;
 
+    fn texts(lines: Vec<LogLine>) -> Vec<String> {
+        lines.into_iter().map(|line| line.text).collect()
+    }
+
     #[test]
     fn test_get_lines_normal() {
         let mut buffer = LogBuffer::new();
-        buffer.add_line("line 1".to_string());
-        buffer.add_line("line 2".to_string());
-        buffer.add_line("line 3".to_string());
-        buffer.add_line("line 4".to_string());
-        buffer.add_line("line 5".to_string());
-        buffer.add_line("line 6".to_string());
-        buffer.add_line("line 7".to_string());
-        buffer.add_line("line 8".to_string());
-        buffer.add_line("line 9".to_string());
-        buffer.add_line("line 10".to_string());
+        buffer.add_line(LogLine::new(0, "line 1"));
+        buffer.add_line(LogLine::new(0, "line 2"));
+        buffer.add_line(LogLine::new(0, "line 3"));
+        buffer.add_line(LogLine::new(0, "line 4"));
+        buffer.add_line(LogLine::new(0, "line 5"));
+        buffer.add_line(LogLine::new(0, "line 6"));
+        buffer.add_line(LogLine::new(0, "line 7"));
+        buffer.add_line(LogLine::new(0, "line 8"));
+        buffer.add_line(LogLine::new(0, "line 9"));
+        buffer.add_line(LogLine::new(0, "line 10"));
 
         let lines = buffer.get_lines(1, 3);
         assert_eq!(lines.len(), 3);
-        assert_eq!(lines[0], "line 2".to_string());
-        assert_eq!(lines[1], "line 3".to_string());
-        assert_eq!(lines[2], "line 4".to_string());
+        assert_eq!(lines[0].text, "line 2");
+        assert_eq!(lines[1].text, "line 3");
+        assert_eq!(lines[2].text, "line 4");
     }
 
     #[test]
     fn test_get_lines_overflow() {
         let mut buffer = LogBuffer::new();
-        buffer.add_line("line 1".to_string());
-        buffer.add_line("line 2".to_string());
-        buffer.add_line("line 3".to_string());
-        buffer.add_line("line 4".to_string());
-        buffer.add_line("line 5".to_string());
-        buffer.add_line("line 6".to_string());
-        buffer.add_line("line 7".to_string());
-        buffer.add_line("line 8".to_string());
-        buffer.add_line("line 9".to_string());
-        buffer.add_line("line 10".to_string());
+        buffer.add_line(LogLine::new(0, "line 1"));
+        buffer.add_line(LogLine::new(0, "line 2"));
+        buffer.add_line(LogLine::new(0, "line 3"));
+        buffer.add_line(LogLine::new(0, "line 4"));
+        buffer.add_line(LogLine::new(0, "line 5"));
+        buffer.add_line(LogLine::new(0, "line 6"));
+        buffer.add_line(LogLine::new(0, "line 7"));
+        buffer.add_line(LogLine::new(0, "line 8"));
+        buffer.add_line(LogLine::new(0, "line 9"));
+        buffer.add_line(LogLine::new(0, "line 10"));
 
         let lines = buffer.get_lines(7, 4);
         assert_eq!(lines.len(), 3);
-        assert_eq!(lines[0], "line 8".to_string());
-        assert_eq!(lines[1], "line 9".to_string());
-        assert_eq!(lines[2], "line 10".to_string());
+        assert_eq!(lines[0].text, "line 8");
+        assert_eq!(lines[1].text, "line 9");
+        assert_eq!(lines[2].text, "line 10");
+    }
+
+    #[test]
+    fn test_get_lines_past_u16_range() {
+        let mut buffer = LogBuffer::new();
+        for i in 1..=3_000_000 {
+            buffer.add_line(LogLine::new(0, format!("line {}", i)));
+        }
+
+        let lines = texts(buffer.get_lines(2_999_997, 5));
+        assert_eq!(lines, vec!["line 2999998", "line 2999999", "line 3000000"]);
+
+        let lines = texts(buffer.get_lines(65_536, 1));
+        assert_eq!(lines, vec!["line 65537"]);
+    }
+
+    #[test]
+    fn test_mapped_get_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, b"\xef\xbb\xbfline 1\nline 2\r\n\xff\nline 4").unwrap();
+
+        let buffer = LogBuffer::open_mapped(&path, None).unwrap();
+        while buffer.index_progress().is_some() {
+            std::thread::yield_now();
+        }
+
+        assert_eq!(buffer.len(), 4);
+        assert_eq!(
+            texts(buffer.get_lines(0, 10)),
+            vec!["line 1", "line 2", "\\xFF", "line 4"]
+        );
+        assert_eq!(texts(buffer.get_lines(3, 10)), vec!["line 4"]);
+    }
+
+    #[test]
+    fn test_find_time() {
+        let mut buffer = LogBuffer::new();
+        for minute in [0, 10, 10, 20, 40] {
+            let mut line = LogLine::new(0, format!("time=2024-01-15T10:{:02}:00 n=1", minute));
+            line.parse(Some(&LogFormat::Logfmt));
+            buffer.add_line(line);
+            buffer.add_line(LogLine::new(0, "  continued"));
+        }
+        let time = |text| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+
+        assert_eq!(buffer.find_time(time("2024-01-15 10:10")), Some(2));
+        assert_eq!(buffer.find_time(time("2024-01-15 10:12")), Some(2));
+        assert_eq!(buffer.find_time(time("2024-01-15 10:18")), Some(6));
+        assert_eq!(buffer.find_time(time("2024-01-15 09:00")), Some(0));
+        assert_eq!(buffer.find_time(time("2024-01-15 11:00")), Some(8));
+        assert_eq!(buffer.last_timestamp(), Some(time("2024-01-15 10:40")));
+        assert_eq!(LogBuffer::new().find_time(time("2024-01-15 10:10")), None);
+    }
+
+    #[test]
+    fn test_get_lines_out_of_range() {
+        let mut buffer = LogBuffer::new();
+        assert!(buffer.get_lines(0, 10).is_empty());
+
+        buffer.add_line(LogLine::new(0, "line 1"));
+        assert!(buffer.get_lines(u64::MAX, 10).is_empty());
     }
 }
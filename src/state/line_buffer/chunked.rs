@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::state::line_buffer::LogLine;
+
+/// Number of lines in each chunk.
+const CHUNK_LEN: usize = 8192;
+
+/// Lines held in memory in fixed-size chunks. A chunk is never changed once it is full, so full
+/// chunks can be shared with other threads, like a background search, without copying or locking.
+#[derive(Default)]
+pub struct ChunkedLines {
+    full: Vec<Arc<[LogLine]>>,
+    tail: Vec<LogLine>,
+}
+
+impl ChunkedLines {
+    pub fn push(&mut self, line: LogLine) {
+        self.tail.push(line);
+        if self.tail.len() == CHUNK_LEN {
+            self.full.push(Arc::from(std::mem::take(&mut self.tail)));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.full.len() * CHUNK_LEN + self.tail.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&LogLine> {
+        let (chunk, offset) = (index / CHUNK_LEN, index % CHUNK_LEN);
+        match self.full.get(chunk) {
+            Some(lines) => lines.get(offset),
+            None if chunk == self.full.len() => self.tail.get(offset),
+            None => None,
+        }
+    }
+
+    /// The line at `index`, to be changed. A full chunk that is shared with a snapshot is copied
+    /// first, so the snapshot doesn't see the change.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut LogLine> {
+        let (chunk, offset) = (index / CHUNK_LEN, index % CHUNK_LEN);
+        let full = self.full.len();
+        match self.full.get_mut(chunk) {
+            Some(lines) => Arc::make_mut(lines).get_mut(offset),
+            None if chunk == full => self.tail.get_mut(offset),
+            None => None,
+        }
+    }
+
+    pub fn iter_from(&self, start: usize) -> impl Iterator<Item = &LogLine> {
+        let (chunk, offset) = (start / CHUNK_LEN, start % CHUNK_LEN);
+        self.full
+            .iter()
+            .map(|lines| &lines[..])
+            .chain([&self.tail[..]])
+            .skip(chunk)
+            .enumerate()
+            .flat_map(move |(i, lines)| {
+                let skip = if i == 0 { offset } else { 0 };
+                &lines[skip.min(lines.len())..]
+            })
+    }
+
+    /// A copy of the lines as they are now. Full chunks are shared rather than copied.
+    pub fn snapshot(&self) -> Self {
+        Self {
+            full: self.full.clone(),
+            tail: self.tail.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let mut lines = ChunkedLines::default();
+        for i in 0..CHUNK_LEN * 2 + 10 {
+            lines.push(LogLine::new(0, i.to_string()));
+        }
+        let snapshot = lines.snapshot();
+        lines.push(LogLine::new(0, "new"));
+
+        assert_eq!(snapshot.len(), CHUNK_LEN * 2 + 10);
+        assert_eq!(lines.len(), CHUNK_LEN * 2 + 11);
+        assert_eq!(snapshot.get(CHUNK_LEN).unwrap().text, CHUNK_LEN.to_string());
+        assert!(snapshot.get(CHUNK_LEN * 2 + 10).is_none());
+        assert!(snapshot.get(CHUNK_LEN * 5).is_none());
+
+        let texts = snapshot
+            .iter_from(CHUNK_LEN - 1)
+            .take(3)
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>();
+        let expected = (CHUNK_LEN - 1..CHUNK_LEN + 2)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, expected);
+        assert_eq!(
+            lines.iter_from(CHUNK_LEN * 2 + 10).next().unwrap().text,
+            "new"
+        );
+        assert_eq!(lines.iter_from(CHUNK_LEN * 9).count(), 0);
+    }
+}
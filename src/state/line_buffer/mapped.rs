@@ -0,0 +1,134 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use memmap2::Mmap;
+
+use crate::encoding::Encoding;
+
+/// How many bytes the indexer scans before publishing the line endings it found.
+const INDEX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// A read-only file that is memory-mapped and split into lines by a background thread.
+/// Lines are only decoded when they are read, so opening a file costs nothing up front. Clones
+/// share the same map and index.
+#[derive(Clone)]
+pub struct MappedFile {
+    mmap: Arc<Mmap>,
+    index: Arc<LineIndex>,
+    /// Must be ASCII-compatible, since lines are split on `\n` bytes.
+    encoding: Encoding,
+    /// Length of the byte order mark at the start of the file, which isn't part of the first line.
+    bom_length: usize,
+}
+
+#[derive(Default)]
+struct LineIndex {
+    /// Byte offset of the end of each line (the position of its `\n`, or the end of the file).
+    ends: RwLock<Vec<u64>>,
+    indexed_bytes: AtomicU64,
+    complete: AtomicBool,
+}
+
+impl MappedFile {
+    /// Map the file at `path`, decoding it with `encoding` or, if that's `None`, the encoding
+    /// indicated by its byte order mark.
+    pub fn open(path: &Path, encoding: Option<Encoding>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only; if another process truncates the file while we hold it,
+        // reads past the new end will fault. This is the same trade-off `less` and friends make.
+        let mmap = Arc::new(unsafe { Mmap::map(&file)? });
+        let index = Arc::new(LineIndex::default());
+
+        std::thread::spawn({
+            let mmap = mmap.clone();
+            let index = index.clone();
+            move || build_index(&mmap, &index)
+        });
+
+        let (detected, bom_length) = Encoding::detect_bom(&mmap).unwrap_or((Encoding::Utf8, 0));
+        let encoding = encoding.unwrap_or(detected);
+        let bom_length = if encoding == detected { bom_length } else { 0 };
+
+        Ok(Self {
+            mmap,
+            index,
+            encoding,
+            bom_length,
+        })
+    }
+
+    /// Number of lines indexed so far.
+    pub fn len(&self) -> usize {
+        self.index.ends.read().unwrap().len()
+    }
+
+    /// Fraction of the file that has been indexed, or `None` once indexing has finished.
+    pub fn index_progress(&self) -> Option<f64> {
+        if self.index.complete.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let indexed = self.index.indexed_bytes.load(Ordering::Relaxed);
+        Some(indexed as f64 / self.mmap.len().max(1) as f64)
+    }
+
+    pub fn get_lines(&self, start: usize, count: usize) -> Vec<String> {
+        let ends = self.index.ends.read().unwrap();
+        let start = start.min(ends.len());
+        let end = (start + count).min(ends.len());
+        (start..end)
+            .map(|i| self.decode(&ends, i).into_owned())
+            .collect()
+    }
+
+    pub fn iter_from(&self, start: usize) -> impl Iterator<Item = Cow<'_, str>> {
+        // Only take the lock per line so the indexer isn't blocked while the caller iterates.
+        (start..self.len()).map(move |i| self.decode(&self.index.ends.read().unwrap(), i))
+    }
+
+    fn decode(&self, ends: &[u64], index: usize) -> Cow<'_, str> {
+        let start = if index == 0 {
+            self.bom_length
+        } else {
+            ends[index - 1] as usize + 1
+        };
+        let mut line = &self.mmap[start..ends[index] as usize];
+        if let Some(stripped) = line.strip_suffix(b"\r") {
+            line = stripped;
+        }
+        match (self.encoding, std::str::from_utf8(line)) {
+            (Encoding::Utf8, Ok(text)) => Cow::Borrowed(text),
+            _ => Cow::Owned(self.encoding.decode(line)),
+        }
+    }
+}
+
+fn build_index(data: &[u8], index: &LineIndex) {
+    for (chunk_num, chunk) in data.chunks(INDEX_CHUNK_SIZE).enumerate() {
+        let offset = (chunk_num * INDEX_CHUNK_SIZE) as u64;
+        let ends = memchr::memchr_iter(b'\n', chunk)
+            .map(|i| offset + i as u64)
+            .collect::<Vec<_>>();
+
+        index.ends.write().unwrap().extend(ends);
+        index
+            .indexed_bytes
+            .store(offset + chunk.len() as u64, Ordering::Relaxed);
+    }
+
+    // A final line without a trailing newline still counts as a line.
+    let mut ends = index.ends.write().unwrap();
+    let last_start = ends.last().map(|&end| end + 1).unwrap_or(0);
+    if (last_start as usize) < data.len() {
+        ends.push(data.len() as u64);
+    }
+    index.complete.store(true, Ordering::Release);
+}
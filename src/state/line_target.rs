@@ -0,0 +1,90 @@
+use chrono::NaiveDateTime;
+
+use crate::timestamp::parse_time_from;
+
+/// Where the go-to-line prompt was asked to go.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineTarget {
+    /// A one-based line number.
+    Line(u64),
+    /// Some number of visible lines below the top line, or above it if negative.
+    Offset(i64),
+    /// Some percentage of the way through the visible lines.
+    Percent(f64),
+    /// The last visible line.
+    Last,
+    /// A line marked with `m`.
+    Mark(char),
+    /// The line logged closest to a time.
+    Time(NaiveDateTime),
+}
+
+impl LineTarget {
+    /// Parse what was typed into the go-to-line prompt: `120`, `+200`, `-50`, `75%`, `$`, `'a`,
+    /// or a time in any form `parse_time_from` takes, going by `now`.
+    pub fn parse(text: &str, now: NaiveDateTime) -> Option<Self> {
+        let text = text.trim();
+        // Only plain digits, since `parse` would also take a sign.
+        let digits = |text: &str| {
+            text.bytes()
+                .all(|b| b.is_ascii_digit())
+                .then(|| text.parse::<u64>().ok())
+                .flatten()
+        };
+
+        if let Some(line) = digits(text) {
+            return Some(Self::Line(line));
+        }
+        if let Some(offset) = text.strip_prefix('+').and_then(digits) {
+            return Some(Self::Offset(offset.try_into().unwrap_or(i64::MAX)));
+        }
+        if let Some(offset) = text.strip_prefix('-').and_then(digits) {
+            return Some(Self::Offset(-offset.try_into().unwrap_or(i64::MAX)));
+        }
+        if let Some(Ok(percent)) = text.strip_suffix('%').map(|p| p.trim().parse::<f64>()) {
+            return (0.0..=100.0)
+                .contains(&percent)
+                .then_some(Self::Percent(percent));
+        }
+        if text == "$" {
+            return Some(Self::Last);
+        }
+        if let Some(mark) = text.strip_prefix('\'') {
+            let mut chars = mark.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(mark), None) => Some(Self::Mark(mark)),
+                _ => None,
+            };
+        }
+        parse_time_from(text, now).map(Self::Time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let now = NaiveDateTime::parse_from_str("2026-10-17 14:30", "%Y-%m-%d %H:%M").unwrap();
+        let parse = |text| LineTarget::parse(text, now);
+        assert_eq!(parse("120"), Some(LineTarget::Line(120)));
+        assert_eq!(parse(" +200"), Some(LineTarget::Offset(200)));
+        assert_eq!(parse("-50"), Some(LineTarget::Offset(-50)));
+        assert_eq!(parse("75%"), Some(LineTarget::Percent(75.0)));
+        assert_eq!(parse("150%"), None);
+        assert_eq!(parse("$"), Some(LineTarget::Last));
+        assert_eq!(parse("'a"), Some(LineTarget::Mark('a')));
+        assert_eq!(parse("'ab"), None);
+        assert_eq!(
+            parse("-15m"),
+            Some(LineTarget::Time(now - chrono::TimeDelta::minutes(15)))
+        );
+        assert_eq!(
+            parse("14:03"),
+            Some(LineTarget::Time(now - chrono::TimeDelta::minutes(27)))
+        );
+        assert_eq!(parse("+-3"), None);
+        assert_eq!(parse(""), None);
+    }
+}
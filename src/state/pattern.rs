@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CaseMode {
+    /// Ignore case unless the pattern has an uppercase letter in it.
+    #[default]
+    Smart,
+    Insensitive,
+    Sensitive,
+}
+
+/// An option that can be switched from inside a pattern prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternToggle {
+    /// Switch between regex and literal text.
+    Regex,
+    /// Cycle through the `CaseMode`s.
+    Case,
+    WholeWord,
+}
+
+/// How the text typed into a search or filter prompt is matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PatternOptions {
+    /// Treat the pattern as a regular expression rather than literal text.
+    pub regex: bool,
+    pub case: CaseMode,
+    /// Only match where the pattern starts and ends at word boundaries.
+    pub whole_word: bool,
+}
+
+impl Default for PatternOptions {
+    fn default() -> Self {
+        Self {
+            regex: true,
+            case: CaseMode::default(),
+            whole_word: false,
+        }
+    }
+}
+
+impl PatternOptions {
+    pub fn toggle(&mut self, toggle: PatternToggle) {
+        match toggle {
+            PatternToggle::Regex => self.regex = !self.regex,
+            PatternToggle::Case => {
+                self.case = match self.case {
+                    CaseMode::Smart => CaseMode::Insensitive,
+                    CaseMode::Insensitive => CaseMode::Sensitive,
+                    CaseMode::Sensitive => CaseMode::Smart,
+                }
+            }
+            PatternToggle::WholeWord => self.whole_word = !self.whole_word,
+        }
+    }
+
+    /// A short description of the options, like `regex, smart case`.
+    pub fn describe(&self) -> String {
+        let mut modes = vec![
+            if self.regex { "regex" } else { "literal" },
+            match self.case {
+                CaseMode::Smart => "smart case",
+                CaseMode::Insensitive => "ignore case",
+                CaseMode::Sensitive => "match case",
+            },
+        ];
+        if self.whole_word {
+            modes.push("whole word");
+        }
+        modes.join(", ")
+    }
+}
+
+/// A compiled search or filter pattern.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// The pattern as it was typed.
+    pub source: String,
+    pub options: PatternOptions,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn new(source: String, options: PatternOptions) -> Result<Self, regex::Error> {
+        let mut pattern = if options.regex {
+            source.clone()
+        } else {
+            regex::escape(&source)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+
+        let case_insensitive = match options.case {
+            CaseMode::Smart => !has_uppercase(&source, options.regex),
+            CaseMode::Insensitive => true,
+            CaseMode::Sensitive => false,
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(case_insensitive)
+            .build()?;
+
+        Ok(Self {
+            source,
+            options,
+            regex,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// The byte ranges of every match in `text`, each followed by the ranges of its capture
+    /// groups that took part in the match. Each range comes with its group number, where 0 is the
+    /// whole match.
+    pub fn match_ranges(&self, text: &str) -> Vec<(usize, Range<usize>)> {
+        if self.regex.captures_len() == 1 {
+            return self
+                .regex
+                .find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| (0, m.range()))
+                .collect();
+        }
+
+        self.regex
+            .captures_iter(text)
+            .flat_map(|captures| {
+                captures
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(group, m)| Some((group, m?.range())))
+                    .filter(|(_, range)| !range.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Whether `pattern` has an uppercase letter in it, not counting escapes like `\W` in a regex.
+fn has_uppercase(pattern: &str, regex: bool) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if regex && c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(source: &str, options: PatternOptions) -> Pattern {
+        Pattern::new(source.to_string(), options).unwrap()
+    }
+
+    #[test]
+    fn test_case_modes() {
+        let smart = PatternOptions::default();
+        assert!(pattern("error", smart).is_match("ERROR: oops"));
+        assert!(!pattern("Error", smart).is_match("ERROR: oops"));
+        assert!(pattern(r"\Werror", smart).is_match("[ERROR]"));
+
+        let sensitive = PatternOptions {
+            case: CaseMode::Sensitive,
+            ..smart
+        };
+        assert!(!pattern("error", sensitive).is_match("ERROR: oops"));
+    }
+
+    #[test]
+    fn test_literal_and_whole_word() {
+        let literal = PatternOptions {
+            regex: false,
+            ..Default::default()
+        };
+        assert!(pattern("a.b", literal).is_match("x a.b y"));
+        assert!(!pattern("a.b", literal).is_match("x acb y"));
+
+        let whole_word = PatternOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert!(pattern("api|db", whole_word).is_match("[db] lost"));
+        assert!(!pattern("api", whole_word).is_match("rapid"));
+
+        assert!(Pattern::new("(unclosed".to_string(), PatternOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_match_ranges_with_groups() {
+        let p = pattern(r"(\w+)=(\d+)?", PatternOptions::default());
+        assert_eq!(
+            p.match_ranges("a=1 b="),
+            vec![(0, 0..3), (1, 0..1), (2, 2..3), (0, 4..6), (1, 4..5)]
+        );
+        assert_eq!(
+            pattern("api", PatternOptions::default()).match_ranges("[api] api"),
+            vec![(0, 1..4), (0, 6..9)]
+        );
+    }
+}
@@ -0,0 +1,565 @@
+use std::{cmp::Ordering, fmt, ops::Range};
+
+use chrono::NaiveDateTime;
+use regex::Regex;
+
+use crate::{
+    format::{Fields, Level},
+    timestamp::{parse_duration, parse_time_from},
+};
+
+/// Characters that end a bare word, besides whitespace.
+const SPECIAL_CHARS: &[char] = &['(', ')', ',', '"', '\'', '=', '!', '<', '>', '~', '&', '|'];
+
+/// A filter on the parsed fields of lines, like
+/// `level >= warn and component in (auth, api) and latency_ms > 250`.
+///
+/// - Comparisons: `=`, `!=`, `<`, `<=`, `>`, `>=`. Levels are compared by severity, `timestamp`
+///   to a time like `"2024-01-15 10:23"`, `14:03` or `-15m`, durations like `1.5s` to durations,
+///   numbers to numbers, and anything else as text.
+/// - `field ~ /regex/` and `field !~ "regex"`
+/// - `field in (a, b, c)` and `field exists`
+/// - `and`, `or`, `not` (or `&&`, `||`, `!`) and parentheses
+///
+/// Values with spaces or special characters in them are quoted. A line that doesn't have a
+/// field doesn't match any comparison on it, and lines that couldn't be parsed match nothing.
+#[derive(Debug, Clone)]
+pub struct Query {
+    /// The query as it was typed.
+    pub source: String,
+    expr: Expr,
+}
+
+/// Why a query couldn't be parsed, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    /// The character the problem is at.
+    pub column: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering == Ordering::Equal,
+            Self::Ne => ordering != Ordering::Equal,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// A quoted string, which is never taken to be a keyword or number.
+    Quoted(String),
+    /// A `/regex/`, which is only recognized after `~` or `!~`.
+    Regex(String),
+    Open,
+    Close,
+    Comma,
+    Compare(Op),
+    Match,
+    NotMatch,
+    And,
+    Or,
+    Not,
+    In,
+    Exists,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Self::Word(word) => format!("`{}`", word),
+            Self::Quoted(text) => format!("\"{}\"", text),
+            Self::Regex(regex) => format!("/{}/", regex),
+            Self::Open => "`(`".to_string(),
+            Self::Close => "`)`".to_string(),
+            Self::Comma => "`,`".to_string(),
+            Self::Compare(_) | Self::Match | Self::NotMatch => "an operator".to_string(),
+            Self::And => "`and`".to_string(),
+            Self::Or => "`or`".to_string(),
+            Self::Not => "`not`".to_string(),
+            Self::In => "`in`".to_string(),
+            Self::Exists => "`exists`".to_string(),
+        }
+    }
+}
+
+/// A value in a query, read every way it could be compared.
+#[derive(Debug, Clone)]
+struct Literal {
+    text: String,
+    number: Option<f64>,
+    /// In seconds.
+    duration: Option<f64>,
+    time: Option<NaiveDateTime>,
+    level: Option<Level>,
+}
+
+impl Literal {
+    /// Read `text` every way it could be compared, with relative times like `-15m` counting back
+    /// from `now`.
+    fn new(text: String, quoted: bool, now: NaiveDateTime) -> Self {
+        Self {
+            number: (!quoted).then(|| text.parse().ok()).flatten(),
+            duration: (!quoted)
+                .then(|| parse_duration(&text))
+                .flatten()
+                .map(|duration| duration.as_seconds_f64()),
+            time: parse_time_from(&text, now),
+            level: Level::parse(&text),
+            text,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(String),
+    Compare(String, Op, Literal),
+    In(String, Vec<Literal>),
+    Match(String, Regex, bool),
+}
+
+impl Expr {
+    fn matches(&self, fields: &Fields) -> bool {
+        match self {
+            Self::And(left, right) => left.matches(fields) && right.matches(fields),
+            Self::Or(left, right) => left.matches(fields) || right.matches(fields),
+            Self::Not(expr) => !expr.matches(fields),
+            Self::Exists(field) => fields.get(field).is_some(),
+            Self::Compare(field, op, literal) => {
+                compare(fields, field, literal).is_some_and(|ordering| op.accepts(ordering))
+            }
+            Self::In(field, literals) => literals
+                .iter()
+                .any(|literal| compare(fields, field, literal) == Some(Ordering::Equal)),
+            Self::Match(field, regex, negated) => fields
+                .get(field)
+                .is_some_and(|value| regex.is_match(&value) != *negated),
+        }
+    }
+}
+
+/// Compare the value of `field` to `literal`, if the line has the field and they can be compared.
+fn compare(fields: &Fields, field: &str, literal: &Literal) -> Option<Ordering> {
+    match field {
+        "timestamp" => return Some(fields.timestamp?.cmp(&literal.time?)),
+        "level" => return Some(fields.level?.cmp(&literal.level?)),
+        _ => {}
+    }
+
+    let value = fields.get(field)?;
+    if let (Some(duration), Some(value)) = (literal.duration, parse_duration(&value)) {
+        return value.as_seconds_f64().partial_cmp(&duration);
+    }
+    if let (Some(number), Ok(value)) = (literal.number, value.parse::<f64>()) {
+        return value.partial_cmp(&number);
+    }
+    Some(value.as_ref().cmp(literal.text.as_str()))
+}
+
+/// The field a name in a query refers to, allowing the short names logs often use.
+fn field_name(name: &str) -> String {
+    match name {
+        "ts" | "time" => "timestamp",
+        "lvl" | "severity" => "level",
+        "msg" => "message",
+        name => name,
+    }
+    .to_string()
+}
+
+impl Query {
+    /// Parse a query, with relative times in it like `ts > -15m` counting back from `now`.
+    pub fn parse(source: &str, now: NaiveDateTime) -> Result<Self, QueryError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            next: 0,
+            now,
+        };
+        let expr = parser.or()?;
+        if let Some((token, span)) = parser.tokens.get(parser.next) {
+            let message = match token {
+                Token::Close => "`)` without a matching `(`".to_string(),
+                token => format!("expected `and` or `or` before {}", token.describe()),
+            };
+            return Err(parser.error_at(span.start, message));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// Whether a line with `fields` matches. Lines that couldn't be parsed never do.
+    pub fn matches(&self, fields: Option<&Fields>) -> bool {
+        fields.is_some_and(|fields| self.expr.matches(fields))
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Range<usize>)>, QueryError> {
+    let error = |at: usize, message: &str| QueryError {
+        message: message.to_string(),
+        column: source[..at].chars().count(),
+    };
+    let mut tokens: Vec<(Token, Range<usize>)> = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|&(_, c)| c == expected).is_some();
+        let after_match = matches!(tokens.last(), Some((Token::Match | Token::NotMatch, _)));
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '=' => {
+                next_is('=');
+                Token::Compare(Op::Eq)
+            }
+            '!' if next_is('=') => Token::Compare(Op::Ne),
+            '!' if next_is('~') => Token::NotMatch,
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Compare(Op::Le),
+            '<' => Token::Compare(Op::Lt),
+            '>' if next_is('=') => Token::Compare(Op::Ge),
+            '>' => Token::Compare(Op::Gt),
+            '~' => Token::Match,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '&' | '|' => return Err(error(start, &format!("`{}` should be doubled", c))),
+            '"' | '\'' | '/' if c != '/' || after_match => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            // Backslashes are kept in regexes, which have escapes of their own.
+                            Some((_, escaped)) if escaped == c || c != '/' => text.push(escaped),
+                            Some((_, escaped)) => {
+                                text.push('\\');
+                                text.push(escaped);
+                            }
+                            None => return Err(error(start, "unclosed quote")),
+                        },
+                        Some((_, end)) if end == c => break,
+                        Some((_, char)) => text.push(char),
+                        None if c == '/' => return Err(error(start, "unclosed regex")),
+                        None => return Err(error(start, "unclosed quote")),
+                    }
+                }
+                if c == '/' {
+                    Token::Regex(text)
+                } else {
+                    Token::Quoted(text)
+                }
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|&(_, c)| !c.is_whitespace() && !SPECIAL_CHARS.contains(&c))
+                {
+                    word.push(c);
+                }
+                match word.to_ascii_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "in" => Token::In,
+                    "exists" => Token::Exists,
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        let end = chars.peek().map_or(source.len(), |&(end, _)| end);
+        tokens.push((token, start..end));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+    next: usize,
+    /// What relative times count back from.
+    now: NaiveDateTime,
+}
+
+impl Parser<'_> {
+    fn error_at(&self, at: usize, message: String) -> QueryError {
+        QueryError {
+            message,
+            column: self.source[..at].chars().count(),
+        }
+    }
+
+    /// An error about the next token, or the end of the query if there isn't one.
+    fn unexpected(&self, expected: &str) -> QueryError {
+        match self.tokens.get(self.next) {
+            Some((token, span)) => self.error_at(
+                span.start,
+                format!("expected {}, found {}", expected, token.describe()),
+            ),
+            None => self.error_at(self.source.len(), format!("expected {}", expected)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn take(&mut self) -> Option<(Token, Range<usize>)> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += token.is_some() as usize;
+        token
+    }
+
+    /// Undo taking `token`, which is `None` at the end of the query.
+    fn put_back(&mut self, token: Option<(Token, Range<usize>)>) {
+        self.next -= token.is_some() as usize;
+    }
+
+    fn take_if(&mut self, expected: &Token) -> bool {
+        let matches = self.peek() == Some(expected);
+        self.next += matches as usize;
+        matches
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.take_if(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.not()?;
+        while self.take_if(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.take_if(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> Result<Expr, QueryError> {
+        let field = match self.take() {
+            Some((Token::Open, span)) => {
+                let expr = self.or()?;
+                if !self.take_if(&Token::Close) {
+                    return match self.peek() {
+                        Some(_) => Err(self.unexpected("`)`")),
+                        None => Err(self.error_at(span.start, "unclosed `(`".to_string())),
+                    };
+                }
+                return Ok(expr);
+            }
+            Some((Token::Word(word) | Token::Quoted(word), _)) => field_name(&word),
+            token => {
+                self.put_back(token);
+                return Err(self.unexpected("a field name"));
+            }
+        };
+
+        match self.take() {
+            Some((Token::Exists, _)) => Ok(Expr::Exists(field)),
+            Some((Token::Compare(op), _)) => {
+                let literal = self.literal(&field)?;
+                Ok(Expr::Compare(field, op, literal))
+            }
+            Some((Token::In, _)) => {
+                if !self.take_if(&Token::Open) {
+                    return Err(self.unexpected("`(`"));
+                }
+                let mut literals = vec![self.literal(&field)?];
+                while self.take_if(&Token::Comma) {
+                    literals.push(self.literal(&field)?);
+                }
+                if !self.take_if(&Token::Close) {
+                    return Err(self.unexpected("`,` or `)`"));
+                }
+                Ok(Expr::In(field, literals))
+            }
+            Some((token @ (Token::Match | Token::NotMatch), _)) => {
+                let (pattern, span) = match self.take() {
+                    Some((Token::Regex(text) | Token::Quoted(text) | Token::Word(text), span)) => {
+                        (text, span)
+                    }
+                    token => {
+                        self.put_back(token);
+                        return Err(self.unexpected("a regex"));
+                    }
+                };
+                let regex = Regex::new(&pattern).map_err(|e| {
+                    let message = e.to_string();
+                    // The last line of a regex error says what's wrong; the rest draws the pattern.
+                    let problem = message.lines().last().unwrap_or("").trim();
+                    self.error_at(span.start, format!("invalid regex: {}", problem))
+                })?;
+                Ok(Expr::Match(field, regex, token == Token::NotMatch))
+            }
+            token => {
+                self.put_back(token);
+                Err(self.unexpected(&format!("an operator after `{}`", field)))
+            }
+        }
+    }
+
+    /// A value to compare `field` to, checking it's a kind the field can be compared with.
+    fn literal(&mut self, field: &str) -> Result<Literal, QueryError> {
+        let (literal, span) = match self.take() {
+            Some((Token::Word(text), span)) => (Literal::new(text, false, self.now), span),
+            Some((Token::Quoted(text), span)) => (Literal::new(text, true, self.now), span),
+            token => {
+                self.put_back(token);
+                return Err(self.unexpected("a value"));
+            }
+        };
+        match field {
+            "timestamp" if literal.time.is_none() => {
+                Err(self.error_at(span.start, format!("`{}` isn't a time", literal.text)))
+            }
+            "level" if literal.level.is_none() => {
+                Err(self.error_at(span.start, format!("`{}` isn't a level", literal.text)))
+            }
+            _ => Ok(literal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        time("2024-01-15 10:30:00")
+    }
+
+    fn fields(level: Level, component: &str, extra: &[(&str, &str)]) -> Fields {
+        Fields {
+            timestamp: Some(time("2024-01-15 10:23:45")),
+            level: Some(level),
+            component: Some(component.to_string()),
+            message: Some("request finished".to_string()),
+            extra: extra
+                .iter()
+                .map(|&(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let slow_api = fields(
+            Level::Warn,
+            "api",
+            &[("latency_ms", "300"), ("took", "1.2s")],
+        );
+        let fast_db = fields(
+            Level::Error,
+            "db",
+            &[("latency_ms", "20"), ("took", "20ms")],
+        );
+        let info = fields(Level::Info, "auth", &[("latency_ms", "900")]);
+        let query = |source: &str| Query::parse(source, now()).unwrap();
+        let matching = |source: &str| {
+            let query = query(source);
+            [&slow_api, &fast_db, &info]
+                .iter()
+                .map(|fields| query.matches(Some(fields)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            matching("level>=warn and component in (auth, api) and latency_ms > 250"),
+            [true, false, false]
+        );
+        assert_eq!(matching("took < 1s"), [false, true, false]);
+        // Values that aren't durations are compared as text instead.
+        assert_eq!(matching("latency_ms != 20ms"), [true, true, true]);
+        assert_eq!(matching("latency_ms = 20ms"), [false, false, false]);
+        assert_eq!(
+            matching("not took exists || lvl = ERROR"),
+            [false, true, true]
+        );
+        assert_eq!(
+            matching("component ~ /^(a|d)/ && msg !~ 'fail'"),
+            [true, true, true]
+        );
+        assert_eq!(
+            matching("(component = api or component = db) and not level = error"),
+            [true, false, false]
+        );
+        assert_eq!(
+            matching("timestamp >= \"2024-01-15 10:23\""),
+            [true, true, true]
+        );
+        assert_eq!(matching("ts > 2024-01-16"), [false, false, false]);
+        assert_eq!(matching("ts > -15m"), [true, true, true]);
+        assert_eq!(matching("ts > '5m ago'"), [false, false, false]);
+        assert!(!query("level exists").matches(None));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| Query::parse(source, now()).unwrap_err();
+        assert_eq!(
+            error("level >= "),
+            QueryError {
+                message: "expected a value".to_string(),
+                column: 9
+            }
+        );
+        assert_eq!(error("level >= loud").message, "`loud` isn't a level");
+        assert_eq!(error("level >= loud").column, 9);
+        assert_eq!(
+            error("status 500").message,
+            "expected an operator after `status`, found `500`"
+        );
+        assert_eq!(error("(a = 1").column, 0);
+        assert_eq!(error("a = 1)").message, "`)` without a matching `(`");
+        assert_eq!(
+            error("a = 1 b = 2").message,
+            "expected `and` or `or` before `b`"
+        );
+        assert_eq!(error("msg = \"oops").message, "unclosed quote");
+        assert!(error("msg ~ /(/").message.starts_with("invalid regex"));
+        assert_eq!(error("ts > yesterday").message, "`yesterday` isn't a time");
+        assert_eq!(error("and").message, "expected a field name, found `and`");
+    }
+}
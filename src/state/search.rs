@@ -0,0 +1,304 @@
+mod job;
+
+use job::SearchJob;
+
+use crate::{
+    state::{line_buffer::LogBuffer, pattern::Pattern, view::View},
+    ui::display_line::display_text,
+};
+
+/// Searches of fewer lines than this are done right away on the UI thread rather than in the
+/// background.
+const BACKGROUND_THRESHOLD: usize = 50_000;
+
+/// A search and the buffer lines that match it. Large searches run in the background, so the
+/// matches fill in over several updates.
+pub struct Search {
+    pub pattern: Pattern,
+    /// Tab width lines are displayed with, since the pattern is matched against the displayed text.
+    tab_width: usize,
+    /// Indices of visible matching lines in the `LogBuffer`, in ascending order.
+    pub matches: Vec<usize>,
+    /// Index into `matches` of the match that was last jumped to.
+    pub current: Option<usize>,
+    /// Indices of every matching line found so far, visible or not, in ascending order.
+    hits: Vec<usize>,
+    /// Number of `hits` that have been checked against the view.
+    hits_checked: usize,
+    /// Buffer lines before this have been searched.
+    searched: usize,
+    job: Option<SearchJob>,
+    /// Whether a background search was stopped before it finished, leaving some lines unsearched.
+    cancelled: bool,
+    /// Buffer index to find the first match at or after, once the search gets far enough.
+    pending_jump: Option<usize>,
+}
+
+impl Clone for Search {
+    /// The clone has the matches found so far. A search still running in the background carries
+    /// on from where it got to the next time the clone is updated.
+    fn clone(&self) -> Self {
+        Self {
+            pattern: self.pattern.clone(),
+            tab_width: self.tab_width,
+            matches: self.matches.clone(),
+            current: self.current,
+            hits: self.hits.clone(),
+            hits_checked: self.hits_checked,
+            searched: self.searched,
+            job: None,
+            cancelled: self.cancelled,
+            pending_jump: None,
+        }
+    }
+}
+
+impl Search {
+    pub fn new(pattern: Pattern, tab_width: usize, lines: &LogBuffer, view: &View) -> Self {
+        let mut search = Self {
+            pattern,
+            tab_width,
+            matches: Vec::new(),
+            current: None,
+            hits: Vec::new(),
+            hits_checked: 0,
+            searched: 0,
+            job: None,
+            cancelled: false,
+            pending_jump: None,
+        };
+        search.update(lines, view);
+        search
+    }
+
+    /// Check every match against the view again, e.g. after lines were hidden or shown.
+    pub fn view_changed(&mut self) {
+        self.matches.clear();
+        self.current = None;
+        self.hits_checked = 0;
+    }
+
+    /// Collect results from the background and search any lines that were added since the last
+    /// update.
+    pub fn update(&mut self, lines: &LogBuffer, view: &View) {
+        if let Some(job) = self.job.as_mut() {
+            self.searched = job.receive(&mut self.hits);
+            if job.is_done() {
+                self.job = None;
+            }
+        }
+
+        let end = view.scanned();
+        if self.job.is_none() && self.searched < end {
+            if end - self.searched < BACKGROUND_THRESHOLD {
+                for (index, text) in (self.searched..end).zip(lines.texts_from(self.searched)) {
+                    if self.pattern.is_match(&display_text(&text, self.tab_width)) {
+                        self.hits.push(index);
+                    }
+                }
+                self.searched = end;
+            } else {
+                let range = self.searched..end;
+                self.job = Some(SearchJob::spawn(
+                    &self.pattern,
+                    self.tab_width,
+                    lines.snapshot(),
+                    range,
+                ));
+            }
+        }
+
+        self.check_hits(view);
+    }
+
+    /// Add the hits that haven't been checked against the view yet to `matches` if they're
+    /// visible.
+    fn check_hits(&mut self, view: &View) {
+        for &hit in &self.hits[self.hits_checked..] {
+            if view.contains(hit) {
+                self.matches.push(hit);
+            }
+        }
+        self.hits_checked = self.hits.len();
+    }
+
+    /// Fraction of the search done in the background so far, or `None` if nothing is running.
+    pub fn progress(&self) -> Option<f64> {
+        self.job.as_ref().map(SearchJob::progress)
+    }
+
+    /// Stop the search running in the background, keeping the matches that were already found,
+    /// even those that hadn't been collected yet. The lines it hadn't got to are skipped, but
+    /// lines added later are still searched.
+    pub fn cancel(&mut self, view: &View) {
+        if let Some(job) = self.job.take() {
+            self.searched = job.cancel(&mut self.hits);
+            self.cancelled = true;
+            self.check_hits(view);
+        }
+        self.pending_jump = None;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Ask to jump to the first match at or after `line` once it's known, which for a search
+    /// still running in the background may be a few updates away.
+    pub fn jump_from(&mut self, line: usize) {
+        self.pending_jump = Some(line);
+    }
+
+    /// Select the match asked for with `jump_from`, if the search has got far enough to know
+    /// which it is. Returns the line index of the selected match.
+    pub fn take_jump(&mut self) -> Option<usize> {
+        let from = self.pending_jump?;
+        // Matches are found in order, so the first one at or after `from` can't change.
+        let found = self.matches.last().is_some_and(|&last| last >= from);
+        if found || self.job.is_none() {
+            self.pending_jump = None;
+            return self.first_from(from);
+        }
+        None
+    }
+
+    /// Select the first match at or after `line`, wrapping around to the first match in the
+    /// buffer. Returns the line index of the selected match.
+    pub fn first_from(&mut self, line: usize) -> Option<usize> {
+        self.select(self.matches.partition_point(|&m| m < line))
+    }
+
+    /// Select the first match after `line`, wrapping around to the first match in the buffer.
+    /// Returns the line index of the selected match.
+    pub fn next_after(&mut self, line: usize) -> Option<usize> {
+        self.select(self.matches.partition_point(|&m| m <= line))
+    }
+
+    /// Select the last match before `line`, wrapping around to the last match in the buffer.
+    /// Returns the line index of the selected match.
+    pub fn prev_before(&mut self, line: usize) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+
+        let prev = self.matches.partition_point(|&m| m < line);
+        let prev = if prev == 0 {
+            self.matches.len() - 1
+        } else {
+            prev - 1
+        };
+        self.select(prev)
+    }
+
+    /// Select the match at `index` in `matches`, or the first match if it's out of range.
+    /// Returns the line index of the selected match.
+    pub fn select(&mut self, index: usize) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+
+        let index = if index >= self.matches.len() {
+            0
+        } else {
+            index
+        };
+        self.current = Some(index);
+        Some(self.matches[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{line_buffer::LogLine, pattern::PatternOptions};
+
+    fn search(query: &str) -> Search {
+        let mut buffer = LogBuffer::new();
+        buffer.add_line(LogLine::new(0, "[INFO ] [api] started"));
+        buffer.add_line(LogLine::new(0, "[ERROR] [db] connection lost"));
+        buffer.add_line(LogLine::new(0, "[INFO ] [api] request"));
+        buffer.add_line(LogLine::new(0, "[ERROR] [api] timeout"));
+        buffer.add_line(LogLine::new(0, "[DEBUG] [db] query"));
+
+        let mut view = View::all();
+        view.update(&buffer, |_, _| true);
+        let pattern = Pattern::new(query.to_string(), PatternOptions::default()).unwrap();
+        Search::new(pattern, 8, &buffer, &view)
+    }
+
+    #[test]
+    fn test_next_and_prev_wrap_around() {
+        let mut search = search("ERROR");
+        assert_eq!(search.matches, vec![1, 3]);
+
+        assert_eq!(search.next_after(0), Some(1));
+        assert_eq!(search.next_after(1), Some(3));
+        assert_eq!(search.next_after(3), Some(1));
+        assert_eq!(search.current, Some(0));
+
+        assert_eq!(search.prev_before(3), Some(1));
+        assert_eq!(search.prev_before(1), Some(3));
+        assert_eq!(search.current, Some(1));
+
+        assert_eq!(search.first_from(1), Some(1));
+        assert_eq!(search.first_from(4), Some(1));
+    }
+
+    #[test]
+    fn test_matches_displayed_text() {
+        let mut buffer = LogBuffer::new();
+        buffer.add_line(LogLine::new(0, "\x1b[31mERR\x1b[0mOR: colored"));
+        buffer.add_line(LogLine::new(0, "\x1b[31mplain"));
+        let mut view = View::all();
+        view.update(&buffer, |_, _| true);
+
+        let pattern = |query: &str| Pattern::new(query.to_string(), PatternOptions::default());
+        let search = Search::new(pattern("ERROR").unwrap(), 8, &buffer, &view);
+        assert_eq!(search.matches, vec![0]);
+        let search = Search::new(pattern(r"\[31m").unwrap(), 8, &buffer, &view);
+        assert!(search.matches.is_empty());
+    }
+
+    #[test]
+    fn test_background_search() {
+        let mut buffer = LogBuffer::new();
+        for i in 0..200_000 {
+            buffer.add_line(LogLine::new(0, format!("line {}", i)));
+        }
+        let mut view = View::filtered();
+        view.update(&buffer, |line, _| !line.text.ends_with('0'));
+
+        let pattern = Pattern::new(r"99\d$".to_string(), PatternOptions::default()).unwrap();
+        let mut search = Search::new(pattern, 8, &buffer, &view);
+        search.jump_from(150_000);
+        while search.progress().is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            search.update(&buffer, &view);
+        }
+
+        // Every number ending in 99 and one more digit, other than 0, below 200,000.
+        assert_eq!(search.matches.len(), 200 * 9);
+        assert!(search.matches.is_sorted());
+        assert_eq!(search.take_jump(), Some(150_991));
+    }
+
+    #[test]
+    fn test_lines_added_after_cancel_are_searched() {
+        let mut buffer = LogBuffer::new();
+        for i in 0..200_000 {
+            buffer.add_line(LogLine::new(0, format!("line {}", i)));
+        }
+        let mut view = View::all();
+        view.update(&buffer, |_, _| true);
+
+        let pattern = Pattern::new("new".to_string(), PatternOptions::default()).unwrap();
+        let mut search = Search::new(pattern, 8, &buffer, &view);
+        search.cancel(&view);
+        assert!(search.is_cancelled());
+
+        buffer.add_line(LogLine::new(0, "new line"));
+        view.update(&buffer, |_, _| true);
+        search.update(&buffer, &view);
+        assert_eq!(search.matches, vec![200_000]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+};
+
+use crate::{
+    state::{line_buffer::LogBuffer, pattern::Pattern},
+    ui::display_line::display_text,
+};
+
+/// Number of lines a worker searches at a time before reporting back and checking whether the
+/// search was cancelled.
+const CHUNK_LEN: usize = 65_536;
+
+/// A search of a range of buffer lines on worker threads. The range is split into chunks that the
+/// workers take in turn, and results are handed back in buffer order as chunks finish.
+pub struct SearchJob {
+    rx: Receiver<(usize, Vec<usize>)>,
+    cancelled: Arc<AtomicBool>,
+    range: Range<usize>,
+    chunks: usize,
+    /// Results for chunks that finished before an earlier one did, by chunk number.
+    pending: BTreeMap<usize, Vec<usize>>,
+    /// The chunk whose results are needed next to keep them in order.
+    next_chunk: usize,
+}
+
+impl SearchJob {
+    /// Start searching `range` of `lines`, which should be a snapshot of the buffer, matching
+    /// against the text as displayed with `tab_width`.
+    pub fn spawn(
+        pattern: &Pattern,
+        tab_width: usize,
+        lines: LogBuffer,
+        range: Range<usize>,
+    ) -> Self {
+        let chunks = range.len().div_ceil(CHUNK_LEN);
+        let (tx, rx) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let next = Arc::new(AtomicUsize::new(0));
+        let lines = Arc::new(lines);
+
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(chunks);
+        for _ in 0..workers {
+            let (tx, cancelled, next, lines) =
+                (tx.clone(), cancelled.clone(), next.clone(), lines.clone());
+            let pattern = pattern.clone();
+            let range = range.clone();
+
+            std::thread::spawn(move || loop {
+                let chunk = next.fetch_add(1, Ordering::Relaxed);
+                if chunk >= chunks || cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let start = range.start + chunk * CHUNK_LEN;
+                let end = (start + CHUNK_LEN).min(range.end);
+                let hits = (start..end)
+                    .zip(lines.texts_from(start))
+                    .filter(|(_, text)| pattern.is_match(&display_text(text, tab_width)))
+                    .map(|(index, _)| index)
+                    .collect();
+                if tx.send((chunk, hits)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self {
+            rx,
+            cancelled,
+            range,
+            chunks,
+            pending: BTreeMap::new(),
+            next_chunk: 0,
+        }
+    }
+
+    /// Add the matching lines that are ready, in order, to `hits`. Returns the end of the range
+    /// of lines that has been searched so far.
+    pub fn receive(&mut self, hits: &mut Vec<usize>) -> usize {
+        self.pending.extend(self.rx.try_iter());
+        while let Some(chunk_hits) = self.pending.remove(&self.next_chunk) {
+            hits.extend(chunk_hits);
+            self.next_chunk += 1;
+        }
+        (self.range.start + self.next_chunk * CHUNK_LEN).min(self.range.end)
+    }
+
+    /// Stop the search, adding every match that has been found to `hits`, including those in
+    /// chunks after one that hasn't finished. Returns the end of the range, since the lines that
+    /// weren't searched are given up on.
+    pub fn cancel(mut self, hits: &mut Vec<usize>) -> usize {
+        self.receive(hits);
+        // Later chunks are in order after the ones just received, so `hits` stays sorted.
+        hits.extend(std::mem::take(&mut self.pending).into_values().flatten());
+        self.range.end
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next_chunk == self.chunks
+    }
+
+    /// Fraction of the range that has been searched.
+    pub fn progress(&self) -> f64 {
+        (self.next_chunk + self.pending.len()) as f64 / self.chunks.max(1) as f64
+    }
+}
+
+impl Drop for SearchJob {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{line_buffer::LogLine, pattern::PatternOptions};
+
+    #[test]
+    fn test_cancel_keeps_finished_chunks() {
+        let mut buffer = LogBuffer::new();
+        for i in 0..CHUNK_LEN * 3 {
+            buffer.add_line(LogLine::new(0, format!("line {}", i)));
+        }
+        let pattern = Pattern::new(r"1$".to_string(), PatternOptions::default()).unwrap();
+        let mut job = SearchJob::spawn(&pattern, 8, buffer.snapshot(), 0..CHUNK_LEN * 3);
+
+        // Every worker finishes before the results are looked at.
+        job.pending.extend(job.rx.iter());
+        let mut hits = Vec::new();
+        assert_eq!(job.cancel(&mut hits), CHUNK_LEN * 3);
+        let expected = (0..CHUNK_LEN * 3)
+            .filter(|i| i % 10 == 1)
+            .collect::<Vec<_>>();
+        assert_eq!(hits, expected);
+    }
+}
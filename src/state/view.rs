@@ -0,0 +1,122 @@
+use std::borrow::Cow;
+
+use crate::state::line_buffer::{LineContext, LineContexts, LogBuffer, LogLine};
+
+/// The lines of a `LogBuffer` that are currently visible, in buffer order. Positions in the view
+/// are what scrolling operates on; buffer indices are what line numbers are shown for.
+pub struct View {
+    /// Buffer indices of the visible lines, or `None` if every line is visible.
+    lines: Option<Vec<usize>>,
+    /// Number of buffer lines that have been considered.
+    scanned: usize,
+    /// The context of each line considered, which is carried over from line to line.
+    contexts: LineContexts,
+}
+
+impl View {
+    /// A view that shows every line in the buffer.
+    pub fn all() -> Self {
+        Self {
+            lines: None,
+            scanned: 0,
+            contexts: LineContexts::default(),
+        }
+    }
+
+    /// A view that only shows the lines passed to `update` that are visible.
+    pub fn filtered() -> Self {
+        Self {
+            lines: Some(Vec::new()),
+            scanned: 0,
+            contexts: LineContexts::default(),
+        }
+    }
+
+    /// Consider any lines that were added to the buffer since the last update, along with their
+    /// contexts.
+    pub fn update(&mut self, buffer: &LogBuffer, visible: impl Fn(&LogLine, LineContext) -> bool) {
+        // A mapped buffer keeps growing while it is indexed, so only the lines that are there now
+        // are considered, and the rest are left for the next update.
+        let end = buffer.len();
+        if let Some(lines) = self.lines.as_mut() {
+            let new_lines = buffer.iter_from(self.scanned).take(end - self.scanned);
+            for (index, line) in (self.scanned..).zip(new_lines) {
+                if visible(&line, self.contexts.next(&line)) {
+                    lines.push(index);
+                }
+            }
+        }
+        self.scanned = end;
+    }
+
+    pub fn len(&self) -> usize {
+        match self.lines.as_ref() {
+            Some(lines) => lines.len(),
+            None => self.scanned,
+        }
+    }
+
+    /// Number of buffer lines the view has considered.
+    pub fn scanned(&self) -> usize {
+        self.scanned
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        match self.lines.as_ref() {
+            Some(lines) => lines.binary_search(&index).is_ok(),
+            None => index < self.scanned,
+        }
+    }
+
+    pub fn buffer_index(&self, position: usize) -> Option<usize> {
+        match self.lines.as_ref() {
+            Some(lines) => lines.get(position).copied(),
+            None => (position < self.scanned).then_some(position),
+        }
+    }
+
+    /// Position of the first visible line at or after the buffer index `index`.
+    pub fn position_of(&self, index: usize) -> usize {
+        match self.lines.as_ref() {
+            Some(lines) => lines.partition_point(|&i| i < index),
+            None => index.min(self.scanned),
+        }
+    }
+
+    /// Iterate over the visible lines from `position` onward, along with their buffer indices.
+    pub fn iter_from<'a>(
+        &'a self,
+        buffer: &'a LogBuffer,
+        position: usize,
+    ) -> Box<dyn Iterator<Item = (usize, Cow<'a, LogLine>)> + 'a> {
+        match self.lines.as_ref() {
+            Some(lines) => Box::new(
+                lines[position.min(lines.len())..]
+                    .iter()
+                    .filter_map(|&index| buffer.get_line(index).map(|line| (index, line))),
+            ),
+            None => Box::new(
+                (position..)
+                    .zip(buffer.iter_from(position))
+                    .take(self.scanned.saturating_sub(position)),
+            ),
+        }
+    }
+
+    /// The `count` visible lines starting at `position`, along with their buffer indices.
+    pub fn window(&self, buffer: &LogBuffer, position: usize, count: u16) -> Vec<(usize, LogLine)> {
+        match self.lines.as_ref() {
+            Some(_) => self
+                .iter_from(buffer, position)
+                .take(count as usize)
+                .map(|(index, line)| (index, line.into_owned()))
+                .collect(),
+            None => {
+                let count = (count as usize).min(self.scanned.saturating_sub(position)) as u16;
+                (position..)
+                    .zip(buffer.get_lines(position as u64, count))
+                    .collect()
+            }
+        }
+    }
+}
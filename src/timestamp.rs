@@ -0,0 +1,332 @@
+use std::{ops::Range, sync::LazyLock};
+
+use chrono::{
+    Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+};
+use regex::{Captures, Regex};
+
+/// Matches the first timestamp in a line in any of the formats we recognize:
+///
+/// - ISO 8601 / RFC 3339 and close relatives: `2024-01-15T10:23:45.123Z`, `2024-01-15 10:23:45,123`,
+///   `2024/01/15 10:23:45 +0200`
+/// - Apache/Nginx access logs: `15/Jan/2024:10:23:45 +0000`
+/// - Syslog (RFC 3164), which has no year: `Jan 15 10:23:45`
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        (?P<iso>
+            (?P<iso_y>\d{4})[-/](?P<iso_mo>\d{2})[-/](?P<iso_d>\d{2})
+            [T\ ]
+            (?P<iso_h>\d{2}):(?P<iso_mi>\d{2}):(?P<iso_s>\d{2})
+            (?:[.,](?P<iso_frac>\d{1,9}))?
+            \ ?(?P<iso_tz>Z|[+-]\d{2}:?\d{2})?
+        )
+        | (?P<clf>
+            (?P<clf_d>\d{2})/(?P<clf_mo>[A-Z][a-z]{2})/(?P<clf_y>\d{4})
+            :(?P<clf_h>\d{2}):(?P<clf_mi>\d{2}):(?P<clf_s>\d{2})
+            \ (?P<clf_tz>[+-]\d{4})
+        )
+        | (?P<syslog>
+            \b(?P<sys_mo>Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec)
+            \ {1,2}(?P<sys_d>\d{1,2})
+            \ (?P<sys_h>\d{2}):(?P<sys_mi>\d{2}):(?P<sys_s>\d{2})\b
+        )",
+    )
+    .unwrap()
+});
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Find the first timestamp in `line`. Timestamps that carry a UTC offset are converted to local
+/// time; those that don't are assumed to already be local.
+pub fn find_timestamp(line: &str) -> Option<NaiveDateTime> {
+    let captures = TIMESTAMP.captures(line)?;
+
+    if captures.name("iso").is_some() {
+        let date = date(&captures, "iso_y", "iso_mo", "iso_d")?;
+        let time = date.and_hms_nano_opt(
+            number(&captures, "iso_h")?,
+            number(&captures, "iso_mi")?,
+            number(&captures, "iso_s")?,
+            captures
+                .name("iso_frac")
+                .map_or(Some(0), |f| nanos(f.as_str()))?,
+        )?;
+        match captures.name("iso_tz").map(|tz| tz.as_str()) {
+            Some("Z") => to_local(time, FixedOffset::east_opt(0)?),
+            Some(tz) => to_local(time, offset(tz)?),
+            None => Some(time),
+        }
+    } else if captures.name("clf").is_some() {
+        let month = month(&captures["clf_mo"])?;
+        let date = NaiveDate::from_ymd_opt(
+            number(&captures, "clf_y")?,
+            month,
+            number(&captures, "clf_d")?,
+        )?;
+        let time = date.and_hms_opt(
+            number(&captures, "clf_h")?,
+            number(&captures, "clf_mi")?,
+            number(&captures, "clf_s")?,
+        )?;
+        to_local(time, offset(&captures["clf_tz"])?)
+    } else {
+        let month = month(&captures["sys_mo"])?;
+        let year = Local::now().year();
+        let date = NaiveDate::from_ymd_opt(year, month, number(&captures, "sys_d")?)?;
+        date.and_hms_opt(
+            number(&captures, "sys_h")?,
+            number(&captures, "sys_mi")?,
+            number(&captures, "sys_s")?,
+        )
+    }
+}
+
+/// Formats a time can be typed in besides those `find_timestamp` recognizes, which all need
+/// seconds.
+const DATE_TIME_FORMATS: [&str; 2] = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"];
+const TIME_FORMATS: [&str; 2] = ["%H:%M:%S", "%H:%M"];
+
+/// Parse a time typed in by the user: a timestamp in any format `find_timestamp` recognizes, a
+/// date and time to the minute like `2024-01-15 10:23`, a date, which means its midnight, or a
+/// time of day, which means on `date`.
+fn parse_time_on(text: &str, date: NaiveDate) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if let Some(time) = find_timestamp(text) {
+        return Some(time);
+    }
+    if let Some(time) = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        return Some(time);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(date.and_time(NaiveTime::MIN));
+    }
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(text, format).ok())
+        .map(|time| date.and_time(time))
+}
+
+/// Parse a duration like `250ms`, `1.5s`, `15m`, `2h` or `1d`.
+pub fn parse_duration(text: &str) -> Option<TimeDelta> {
+    let split = text.find(|c: char| c.is_alphabetic() || c == 'µ')?;
+    let (amount, unit) = text.split_at(split);
+    let amount: f64 = amount.parse().ok()?;
+    let nanos_per_unit = match unit {
+        "ns" => 1.0,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        "m" => 60e9,
+        "h" => 3600e9,
+        "d" => 86400e9,
+        _ => return None,
+    };
+    Some(TimeDelta::nanoseconds((amount * nanos_per_unit) as i64))
+}
+
+/// Parse a time typed in by the user like `parse_time_on`, or a time before `now` like `-15m` or
+/// `15m ago`. Either can start with an `@`. A time of day means on the day of `now`.
+pub fn parse_time_from(text: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    let text = text.strip_prefix('@').unwrap_or(text).trim_start();
+    let ago = text.strip_prefix('-').or_else(|| {
+        text.strip_suffix("ago")
+            .filter(|duration| duration.ends_with(char::is_whitespace))
+    });
+    match ago {
+        Some(duration) => {
+            // A negative duration would go forward, so `--5m` isn't five minutes from now.
+            let duration = parse_duration(duration.trim())?;
+            if duration < TimeDelta::zero() {
+                return None;
+            }
+            now.checked_sub_signed(duration)
+        }
+        None => parse_time_on(text, now.date()),
+    }
+}
+
+/// Parse a range of times typed in by the user like `14:00..14:30`, where either end can be left
+/// out. A time on its own, like `-15m`, means from then on. Ranges that end before they start are
+/// rejected rather than matching nothing.
+pub fn parse_time_range(text: &str, now: NaiveDateTime) -> Result<Range<NaiveDateTime>, String> {
+    let (start, end) = text.split_once("..").unwrap_or((text, ""));
+    let time = |text: &str, open| match text.trim() {
+        "" => Some(open),
+        text => parse_time_from(text, now),
+    };
+    let (Some(start), Some(end)) = (
+        time(start, NaiveDateTime::MIN),
+        time(end, NaiveDateTime::MAX),
+    ) else {
+        return Err("expected a range like 14:00..14:30, -15m.. or ..2026-10-17 14:03".to_string());
+    };
+    if end <= start {
+        return Err("the range has to end after it starts".to_string());
+    }
+    Ok(start..end)
+}
+
+fn number<T: std::str::FromStr>(captures: &Captures, name: &str) -> Option<T> {
+    captures.name(name)?.as_str().parse().ok()
+}
+
+fn date(captures: &Captures, year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(
+        number(captures, year)?,
+        number(captures, month)?,
+        number(captures, day)?,
+    )
+}
+
+fn month(name: &str) -> Option<u32> {
+    MONTHS.iter().position(|&m| m == name).map(|m| m as u32 + 1)
+}
+
+/// Convert a fraction of a second like `123` (from `.123`) into nanoseconds.
+fn nanos(fraction: &str) -> Option<u32> {
+    let digits: u32 = fraction.parse().ok()?;
+    Some(digits * 10u32.pow(9 - fraction.len() as u32))
+}
+
+/// Parse a UTC offset like `+0200`, `+02:00` or `-0530`.
+fn offset(tz: &str) -> Option<FixedOffset> {
+    let sign = if tz.starts_with('-') { -1 } else { 1 };
+    let digits = tz[1..].replace(':', "");
+    let hours: i32 = digits.get(..2)?.parse().ok()?;
+    let minutes: i32 = digits.get(2..)?.parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn to_local(time: NaiveDateTime, offset: FixedOffset) -> Option<NaiveDateTime> {
+    let time = offset.from_local_datetime(&time).single()?;
+    Some(time.with_timezone(&Local).naive_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn naive(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    fn utc(s: &str) -> NaiveDateTime {
+        Utc.from_utc_datetime(&naive(s))
+            .with_timezone(&Local)
+            .naive_local()
+    }
+
+    #[test]
+    fn test_iso_timestamps() {
+        assert_eq!(
+            find_timestamp("2024-01-15 10:23:45,123 INFO started"),
+            Some(naive("2024-01-15 10:23:45.123"))
+        );
+        assert_eq!(
+            find_timestamp(r#"{"time":"2024-01-15T10:23:45.5Z","msg":"hi"}"#),
+            Some(utc("2024-01-15 10:23:45.5"))
+        );
+        assert_eq!(
+            find_timestamp("2024/01/15 12:23:45 +02:00 worker ready"),
+            Some(utc("2024-01-15 10:23:45"))
+        );
+    }
+
+    #[test]
+    fn test_access_log_timestamp() {
+        let line = r#"127.0.0.1 - - [15/Jan/2024:10:23:45 -0500] "GET / HTTP/1.1" 200 512"#;
+        assert_eq!(find_timestamp(line), Some(utc("2024-01-15 15:23:45")));
+    }
+
+    #[test]
+    fn test_syslog_timestamp() {
+        let year = Local::now().year();
+        assert_eq!(
+            find_timestamp("Jan  5 10:23:45 host sshd[42]: accepted"),
+            Some(naive(&format!("{}-01-05 10:23:45", year)))
+        );
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        assert_eq!(
+            parse_time_on("2024-01-15 10:23", date),
+            Some(naive("2024-01-15 10:23:00"))
+        );
+        assert_eq!(
+            parse_time_on("2024-01-15", date),
+            Some(naive("2024-01-15 00:00:00"))
+        );
+        assert_eq!(
+            parse_time_on("14:03:22", date),
+            Some(naive("2026-10-17 14:03:22"))
+        );
+        assert_eq!(parse_time_on("yesterday", date), None);
+
+        assert_eq!(parse_duration("250ms"), Some(TimeDelta::milliseconds(250)));
+        assert_eq!(parse_duration("1.5m"), Some(TimeDelta::seconds(90)));
+        assert_eq!(parse_duration("15"), None);
+        assert_eq!(parse_duration("5 parsecs"), None);
+    }
+
+    #[test]
+    fn test_relative_times() {
+        let now = naive("2026-10-17 14:30:00");
+        assert_eq!(
+            parse_time_from("-15m", now),
+            Some(naive("2026-10-17 14:15:00"))
+        );
+        assert_eq!(
+            parse_time_from("@5m ago", now),
+            Some(naive("2026-10-17 14:25:00"))
+        );
+        assert_eq!(
+            parse_time_from("@2026-10-17 14:03", now),
+            Some(naive("2026-10-17 14:03:00"))
+        );
+        assert_eq!(
+            parse_time_from("14:03", now),
+            Some(naive("2026-10-17 14:03:00"))
+        );
+        assert_eq!(parse_time_from("-soon", now), None);
+        assert_eq!(parse_time_from("Chicago", now), None);
+        assert_eq!(parse_time_from("5mago", now), None);
+        assert_eq!(parse_time_from("--5m", now), None);
+        assert_eq!(parse_time_from("-5m ago", now), None);
+
+        assert_eq!(
+            parse_time_range("2026-10-17 14:00..-10m", now),
+            Ok(naive("2026-10-17 14:00:00")..naive("2026-10-17 14:20:00"))
+        );
+        assert_eq!(
+            parse_time_range("..2026-10-17 14:00", now),
+            Ok(NaiveDateTime::MIN..naive("2026-10-17 14:00:00"))
+        );
+        assert_eq!(
+            parse_time_range("1h ago", now),
+            Ok(naive("2026-10-17 13:30:00")..NaiveDateTime::MAX)
+        );
+        assert!(parse_time_range("14:00..later", now).is_err());
+        assert_eq!(
+            parse_time_range("15:00..14:00", now),
+            Err("the range has to end after it starts".to_string())
+        );
+        assert!(parse_time_range("14:00..14:00", now).is_err());
+    }
+
+    #[test]
+    fn test_no_timestamp() {
+        assert_eq!(find_timestamp("[INFO ] [api] no time here"), None);
+        assert_eq!(find_timestamp("2024-13-45 10:23:45 not a date"), None);
+    }
+}
@@ -1,88 +1,910 @@
-use std::{
-    rc::Rc,
-    sync::RwLock,
-};
+use std::{borrow::Cow, ops::Range, rc::Rc, sync::RwLock};
+
+use chrono::{NaiveDateTime, TimeDelta};
 
+use display_line::DisplayLine;
 use ratatui::{
     layout::Flex,
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table},
+};
+use serde_json::Value;
+use widgets::{
+    bar_select::{BarSelect, BarSelectState},
+    list_select::{ListSelect, ListSelectState},
+    text_input::{TextInput, TextInputState},
 };
-use widgets::text_input::TextInput;
 
-use crate::AppState;
+use crate::{
+    format::Level,
+    state::{
+        Bucket, Columns, FacetRow, FieldFilter, Filter, FilterChain, FilterKind, LogLine, NodeKey,
+        Pattern, PatternOptions, Search, Source, SLOTS,
+    },
+    AppState,
+};
 
+pub mod display_line;
 pub mod focusable_input;
+pub mod level_colors;
 pub mod widgets;
 
+/// Source names longer than this are truncated in the gutter.
+const MAX_SOURCE_WIDTH: usize = 16;
+
+const FILTER_PANEL_WIDTH: u16 = 40;
+
+const RESULTS_PANE_HEIGHT: u16 = 12;
+
+const DETAIL_PANE_HEIGHT: u16 = 16;
+
+const HISTOGRAM_HEIGHT: u16 = 8;
+
+/// The levels of the histogram's bars from the bottom up, so the most severe stay level with
+/// each other from bar to bar.
+const HISTOGRAM_STACK: [Option<Level>; SLOTS] = [
+    Some(Level::Fatal),
+    Some(Level::Error),
+    Some(Level::Warn),
+    Some(Level::Info),
+    Some(Level::Debug),
+    Some(Level::Trace),
+    None,
+];
+
+/// Background colors for the capture groups of a search match, which is itself yellow.
+const GROUP_COLORS: [Color; 5] = [
+    Color::LightCyan,
+    Color::LightMagenta,
+    Color::LightGreen,
+    Color::LightBlue,
+    Color::LightRed,
+];
+
+const SOURCE_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Red,
+];
+
 pub fn render(frame: &mut Frame, state: &Rc<RwLock<AppState>>) {
-    let outer_block = Block::default().title("logfile").borders(Borders::ALL);
-    let size = outer_block.inner(frame.area());
-    state.write().unwrap().last_frame_height = size.height;
+    let title = match state.read().unwrap().sources.as_slice() {
+        [source] => match source.format.format() {
+            Some(format) => Line::from(format!("logfile ({})", format)),
+            None => Line::from("logfile"),
+        },
+        sources => source_legend(sources),
+    };
+    let mut outer_block = Block::default().title(title).borders(Borders::ALL);
+    if let Some(progress) = state.read().unwrap().lines.index_progress() {
+        let status = format!(" indexing {:.0}% ", progress * 100.0);
+        outer_block = outer_block.title(Line::from(status).right_aligned());
+    }
+    if state.read().unwrap().filters.is_active() {
+        let state = state.read().unwrap();
+        outer_block = outer_block.title_bottom(filter_status(&state).right_aligned());
+    }
+    if let Some(search) = state.read().unwrap().search.as_ref() {
+        outer_block = outer_block.title_bottom(Line::from(search_status(search)).right_aligned());
+    }
+    if state.read().unwrap().awaiting_mark {
+        outer_block = outer_block.title_bottom(Line::from(" mark line as: ").left_aligned());
+    }
+    if let Some(status) = error_status(&state.read().unwrap().sources) {
+        outer_block = outer_block.title_bottom(status.left_aligned());
+    }
+    if let Some(status) = warning_status(&state.read().unwrap().warnings) {
+        outer_block = outer_block.title_bottom(status.left_aligned());
+    }
+    // The filter panel takes space from the log rather than covering it, so the effect of each
+    // change can be seen.
+    let has_panel = {
+        let state = state.read().unwrap();
+        state.filter_panel.is_some() || state.column_panel.is_some() || state.facet_panel.is_some()
+    };
+    let (area, panel_area) = if has_panel {
+        let [area, panel_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(FILTER_PANEL_WIDTH)])
+                .areas(frame.area());
+        (area, Some(panel_area))
+    } else {
+        (frame.area(), None)
+    };
+    state.write().unwrap().histogram_width = area.width.saturating_sub(2);
+    let (area, histogram_area) = if state.read().unwrap().histogram_pane.is_some() {
+        // Leave at least half of the log in view.
+        let height = HISTOGRAM_HEIGHT.min(area.height / 2);
+        let [histogram_area, area] =
+            Layout::vertical([Constraint::Length(height), Constraint::Fill(1)]).areas(area);
+        (area, Some(histogram_area))
+    } else {
+        (area, None)
+    };
+    let (area, results_area) = if state.read().unwrap().results_pane.is_some() {
+        let [area, results_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(RESULTS_PANE_HEIGHT)])
+                .areas(area);
+        (area, Some(results_area))
+    } else {
+        (area, None)
+    };
+    let (area, detail_area) = if state.read().unwrap().detail_pane.is_some() {
+        // Leave at least half of the log in view.
+        let height = DETAIL_PANE_HEIGHT.min(area.height / 2);
+        let [area, detail_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(height)]).areas(area);
+        (area, Some(detail_area))
+    } else {
+        (area, None)
+    };
+
+    // The table view's header row takes a line from the log.
+    let header_height = state.read().unwrap().table_view as u16;
+    let size = outer_block.inner(area);
+    state.write().unwrap().last_frame_height = size.height.saturating_sub(header_height);
 
     let lines = &state.read().unwrap().get_lines();
 
-    let start_line_no = state.read().unwrap().line_num;
-    let to_line_no = start_line_no + lines.len() as u16;
-    let line_no_length = to_line_no.checked_ilog10().unwrap_or(0) + 1;
+    let last_line_no = lines.last().map(|(index, _)| index + 1).unwrap_or(1);
+    let line_no_length = last_line_no.checked_ilog10().unwrap_or(0) as usize + 1;
+    let show_line_numbers = state.read().unwrap().show_line_numbers;
 
-    let left_col_width = if state.read().unwrap().show_line_numbers {
-        line_no_length as u16 + 2
-    } else {
-        0
+    let source_names = state
+        .read()
+        .unwrap()
+        .sources
+        .iter()
+        .map(|source| {
+            source
+                .name
+                .chars()
+                .take(MAX_SOURCE_WIDTH)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    let source_width = match source_names.as_slice() {
+        [_] => 0,
+        names => names
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0),
     };
 
+    let mut left_col_width = 0;
+    if show_line_numbers {
+        left_col_width += line_no_length + 1;
+    }
+    if source_width > 0 {
+        left_col_width += source_width + 1;
+    }
+    if left_col_width > 0 {
+        left_col_width += 1;
+    }
+    let left_col_width = left_col_width as u16;
+
     let outer_layout = Layout::default()
         .direction(Direction::Horizontal)
         .constraints(vec![
             Constraint::Length(left_col_width),
             Constraint::Percentage(100),
         ])
-        .split(frame.area());
+        .split(area);
 
     if left_col_width > 0 {
         let mut left_frame = outer_layout[0];
-        left_frame.y += 1;
-        left_frame.height -= 1;
+        left_frame.y += 1 + header_height;
+        left_frame.height = left_frame.height.saturating_sub(1 + header_height);
 
-        let line_numbers = (start_line_no..to_line_no)
-            .map(|n| format!(" {:>width$}", n, width = line_no_length as usize))
+        let gutter = lines
+            .iter()
+            .map(|(index, line)| {
+                let mut spans = Vec::new();
+                if show_line_numbers {
+                    spans.push(Span::raw(format!(
+                        " {:>width$}",
+                        index + 1,
+                        width = line_no_length
+                    )));
+                }
+                if source_width > 0 {
+                    spans.push(Span::styled(
+                        format!(
+                            " {:<width$}",
+                            source_names[line.source],
+                            width = source_width
+                        ),
+                        source_color(line.source),
+                    ));
+                }
+                Line::from(spans)
+            })
             .collect::<Vec<_>>();
 
-        let line_numbers = Paragraph::new(Text::from(line_numbers.join("\n")));
-
-        frame.render_widget(line_numbers, left_frame);
+        frame.render_widget(Paragraph::new(gutter), left_frame);
     }
 
-    let log_text = Paragraph::new(Text::from(lines.join("\n"))).block(outer_block);
-
     let right_frame = outer_layout[1];
-    frame.render_widget(log_text, right_frame);
+    if state.read().unwrap().table_view {
+        render_log_table(
+            frame,
+            right_frame,
+            outer_block,
+            &state.read().unwrap(),
+            lines,
+        );
+    } else {
+        render_log_text(
+            frame,
+            right_frame,
+            outer_block,
+            &state.read().unwrap(),
+            lines,
+        );
+    }
 
     let state = state.read().unwrap();
+    if let (Some(panel), Some(panel_area)) = (state.filter_panel.as_ref(), panel_area) {
+        let list = ListSelect::new(filter_items(&state.filters))
+            .titled("Filters")
+            .hint(" i/x add  e edit  v invert  d delete ");
+        let mut translator = panel.lock().unwrap();
+        frame.render_stateful_widget(list, panel_area, translator.input_handler_mut());
+    } else if let (Some(panel), Some(panel_area)) = (state.column_panel.as_ref(), panel_area) {
+        let list = ListSelect::new(column_items(&state.columns))
+            .titled("Columns")
+            .hint(" Space show  J/K move  -/+ width  f fill ");
+        let mut translator = panel.lock().unwrap();
+        frame.render_stateful_widget(list, panel_area, translator.input_handler_mut());
+    } else if let (Some(panel), Some(panel_area)) = (state.facet_panel.as_ref(), panel_area) {
+        let mut translator = panel.lock().unwrap();
+        render_facets(frame, panel_area, &state, translator.input_handler_mut());
+    }
+
+    if let (Some(pane), Some(histogram_area)) = (state.histogram_pane.as_ref(), histogram_area) {
+        let mut translator = pane.lock().unwrap();
+        render_histogram(
+            frame,
+            histogram_area,
+            &state,
+            translator.input_handler_mut(),
+        );
+    }
+    if let (Some(pane), Some(results_area)) = (state.results_pane.as_ref(), results_area) {
+        let mut translator = pane.lock().unwrap();
+        render_results(frame, results_area, &state, translator.input_handler_mut());
+    }
+    if let (Some(pane), Some(detail_area)) = (state.detail_pane.as_ref(), detail_area) {
+        let mut translator = pane.lock().unwrap();
+        render_detail(frame, detail_area, &state, translator.input_handler_mut());
+    }
+
     if let Some(search) = state.search_input.as_ref() {
-        let center = center_inside(frame.area(), frame.area().width - 16, 3);
-        let text_input = TextInput::default().titled("Search");
-        let mut translator = search.lock().unwrap();
-        let input_handler = translator.input_handler_mut();
-
-        frame.render_stateful_widget(text_input, center, input_handler);
-        frame.set_cursor_position((
-            input_handler.cursor_position.0,
-            input_handler.cursor_position.1,
-        ));
+        let title = format!("Search ({})", state.search_options.describe());
+        render_prompt(frame, &title, search.lock().unwrap().input_handler_mut());
+    } else if let Some(filter) = state.filter_input.as_ref() {
+        let title = format!("Filter ({})", state.filter_options.describe());
+        render_prompt(frame, &title, filter.lock().unwrap().input_handler_mut());
+    } else if let Some(query) = state.query_input.as_ref() {
+        let title = "Query (Tab completes field names)";
+        render_prompt(frame, title, query.lock().unwrap().input_handler_mut());
+    } else if let Some(go_to_time) = state.go_to_time.as_ref() {
+        let title = "Go to time (14:03, 2026-10-17 14:03:22, -15m, 5m ago)";
+        render_prompt(frame, title, go_to_time.lock().unwrap().input_handler_mut());
+    } else if let Some(time_range) = state.time_range_input.as_ref() {
+        let title = "Time range (14:00..14:30, -15m.., empty for all)";
+        render_prompt(frame, title, time_range.lock().unwrap().input_handler_mut());
     } else if let Some(go_to_line) = state.go_to_line.as_ref() {
-        let center = center_inside(frame.area(), frame.area().width / 2, 3);
-        let text_input = TextInput::default().titled("Line #");
-        let mut translator = go_to_line.lock().unwrap();
-        let input_handler = translator.input_handler_mut();
-
-        frame.render_stateful_widget(text_input, center, input_handler);
-        frame.set_cursor_position((
-            input_handler.cursor_position.0,
-            input_handler.cursor_position.1,
+        let title = "Line # (+N, -N, N%, $, 'x for a mark, or a time)";
+        let area = render_prompt(frame, title, go_to_line.lock().unwrap().input_handler_mut());
+        let preview_area = Rect {
+            y: area.bottom(),
+            ..area
+        }
+        .intersection(frame.area());
+        render_go_to_line_preview(frame, preview_area, &state);
+    }
+}
+
+/// Show a text prompt in the middle of the screen, returning the area it was drawn in.
+fn render_prompt(frame: &mut Frame, title: &str, input: &mut TextInputState) -> Rect {
+    let area = center_inside(frame.area(), frame.area().width.saturating_sub(16), 3);
+    frame.render_stateful_widget(TextInput::default().titled(title), area, input);
+    frame.set_cursor_position((input.cursor_position.0, input.cursor_position.1));
+    area
+}
+
+/// Show the line the go-to-line prompt would go to, under the prompt.
+fn render_go_to_line_preview(frame: &mut Frame, area: Rect, state: &AppState) {
+    let Some((index, line)) = state
+        .go_to_line_preview
+        .and_then(|index| Some((index, state.lines.get_line(index)?)))
+    else {
+        return;
+    };
+    let display_line = DisplayLine::parse(&line.text, state.tab_width);
+    let preview = Paragraph::new(display_line.to_line(&[]).style(level_style(state, &line))).block(
+        Block::default()
+            .title(format!("Line {}", index + 1))
+            .borders(Borders::ALL),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(preview, area);
+}
+
+fn render_log_text(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    state: &AppState,
+    lines: &[(usize, LogLine)],
+) {
+    let display_lines = lines
+        .iter()
+        .map(|(_, line)| DisplayLine::parse(&line.text, state.tab_width))
+        .collect::<Vec<_>>();
+    let log_text = display_lines
+        .iter()
+        .zip(lines)
+        .map(|(line, (index, log_line))| {
+            line_with_highlights(state, line).style(line_style(state, *index, log_line))
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(Paragraph::new(log_text).block(block), area);
+}
+
+/// Render the lines as a table of their fields. Lines that couldn't be parsed are shown whole in
+/// the message column, or the last column if the message isn't shown.
+fn render_log_table(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    state: &AppState,
+    lines: &[(usize, LogLine)],
+) {
+    let columns = state.columns.visible().collect::<Vec<_>>();
+    let fallback_column = columns
+        .iter()
+        .position(|column| column.field == "message")
+        .unwrap_or(columns.len().saturating_sub(1));
+
+    let display_rows = lines
+        .iter()
+        .map(|(_, line)| {
+            columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    let value = match line.fields.as_ref() {
+                        Some(fields) => fields.get(&column.field),
+                        None if i == fallback_column => Some(Cow::Borrowed(line.text.as_str())),
+                        None => None,
+                    };
+                    value.map(|value| DisplayLine::parse(&value, state.tab_width))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let rows = display_rows
+        .iter()
+        .zip(lines)
+        .map(|(cells, (index, line))| {
+            let cells = cells.iter().map(|cell| match cell {
+                Some(cell) => Cell::from(line_with_highlights(state, cell)),
+                None => Cell::default(),
+            });
+            Row::new(cells).style(line_style(state, *index, line))
+        });
+
+    let header = Row::new(columns.iter().map(|column| column.field.as_str()))
+        .style(Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED));
+    let widths = columns.iter().map(|column| match column.width {
+        Some(width) => Constraint::Length(width),
+        None => Constraint::Fill(1),
+    });
+    let table = Table::new(rows, widths).header(header).block(block);
+    frame.render_widget(table, area);
+}
+
+/// A display line with the matches of the current search highlighted.
+fn line_with_highlights<'a>(state: &AppState, line: &'a DisplayLine) -> Line<'a> {
+    match state.search.as_ref() {
+        Some(search) => line.to_line(&match_highlights(&search.pattern, line.text())),
+        None => line.to_line(&[]),
+    }
+}
+
+fn column_items(columns: &Columns) -> Vec<Line<'static>> {
+    columns
+        .columns
+        .iter()
+        .map(|column| {
+            let checkbox = if column.visible { "[x] " } else { "[ ] " };
+            let width = match column.width {
+                Some(width) => width.to_string(),
+                None => "fill".to_string(),
+            };
+            Line::from(vec![
+                Span::raw(checkbox),
+                Span::raw(column.field.clone()),
+                Span::styled(
+                    format!(" ({})", width),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect()
+}
+
+fn source_color(source: usize) -> Color {
+    SOURCE_COLORS[source % SOURCE_COLORS.len()]
+}
+
+/// A title listing each source with the key that toggles it, dimmed if it is hidden.
+fn source_legend(sources: &[Source]) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    for (index, source) in sources.iter().enumerate() {
+        let style = if source.visible {
+            Style::default().fg(source_color(index))
+        } else {
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::CROSSED_OUT)
+        };
+        spans.push(Span::styled(
+            format!("{}:{}", index + 1, source.name),
+            style,
         ));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
+
+/// Summarize the enabled filters, like `+ERROR -healthcheck`, with how many lines pass them.
+fn filter_status(state: &AppState) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    if let Some(level) = state.filters.min_level {
+        spans.push(Span::raw(format!(">={}", level)));
+        spans.push(Span::raw(" "));
+    }
+    if let Some(query) = state.filters.query.as_ref() {
+        spans.push(Span::styled(format!(":{}", query.source), Color::Cyan));
+        spans.push(Span::raw(" "));
+    }
+    if let Some(range) = state.filters.time_range.as_ref() {
+        spans.push(Span::styled(
+            format!("@{}", time_range_label(range.start, range.end)),
+            Color::Cyan,
+        ));
+        spans.push(Span::raw(" "));
+    }
+    for filter in &state.filters.field_filters {
+        spans.push(field_filter_span(filter));
+        spans.push(Span::raw(" "));
     }
+    for filter in state.filters.filters.iter().filter(|filter| filter.enabled) {
+        spans.push(filter_span(filter));
+        spans.push(Span::raw(" "));
+    }
+    spans.push(Span::raw(format!(
+        "({} of {} lines) ",
+        state.view.len(),
+        state.lines.len()
+    )));
+    Line::from(spans)
+}
+
+fn filter_items(filters: &FilterChain) -> Vec<Line<'static>> {
+    if filters.filters.is_empty() {
+        return vec![Line::styled(
+            "No filters. Press i or x to add one.",
+            Style::default().fg(Color::DarkGray),
+        )];
+    }
+
+    filters
+        .filters
+        .iter()
+        .map(|filter| {
+            let checkbox = if filter.enabled { "[x] " } else { "[ ] " };
+            let mut spans = vec![Span::raw(checkbox), filter_span(filter)];
+            if filter.pattern.options != PatternOptions::default() {
+                spans.push(Span::styled(
+                    format!(" ({})", filter.pattern.options.describe()),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn filter_span(filter: &Filter) -> Span<'static> {
+    let (sign, color) = match filter.kind {
+        FilterKind::Include => ('+', Color::Green),
+        FilterKind::Exclude => ('-', Color::Red),
+    };
+    let color = if filter.enabled {
+        color
+    } else {
+        Color::DarkGray
+    };
+    Span::styled(format!("{}{}", sign, filter.pattern.source), color)
+}
+
+fn field_filter_span(filter: &FieldFilter) -> Span<'static> {
+    let (sign, color) = match filter.kind {
+        FilterKind::Include => ('+', Color::Green),
+        FilterKind::Exclude => ('-', Color::Red),
+    };
+    Span::styled(format!("{}{}={}", sign, filter.field, filter.value), color)
+}
+
+/// List the values of each facet with how many lines have them, marking the ones that are
+/// filtered on.
+fn render_facets(
+    frame: &mut Frame,
+    area: Rect,
+    state: &AppState,
+    list_state: &mut ListSelectState,
+) {
+    let rows = state.facets.rows();
+    let value_width = (area.width as usize).saturating_sub(2 + 2 + 8 + 1);
+    let items = rows
+        .iter()
+        .map(|row| match *row {
+            FacetRow::Field(field) => Line::styled(
+                field.to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            FacetRow::Value {
+                field,
+                value,
+                count,
+            } => {
+                let filter = state
+                    .filters
+                    .field_filters
+                    .iter()
+                    .find(|filter| filter.field == field && filter.value == value);
+                let marker = match filter.map(|filter| filter.kind) {
+                    Some(FilterKind::Include) => Span::styled("+ ", Color::Green),
+                    Some(FilterKind::Exclude) => Span::styled("- ", Color::Red),
+                    None => Span::raw("  "),
+                };
+                let value = value.chars().take(value_width).collect::<String>();
+                let mut style = Style::default();
+                if field == "level" {
+                    if let Some(color) =
+                        Level::parse(&value).and_then(|l| state.level_colors.get(l))
+                    {
+                        style = style.fg(color);
+                    }
+                }
+                Line::from(vec![
+                    marker,
+                    Span::styled(format!("{:<width$}", value, width = value_width), style),
+                    Span::styled(format!(" {:>8}", count), Color::DarkGray),
+                ])
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let title = match state.lines.len() {
+        total if state.facets.counted < total => format!(
+            "Facets (counting {:.0}%)",
+            state.facets.counted as f64 * 100.0 / total as f64
+        ),
+        _ => "Facets".to_string(),
+    };
+    let items = if items.is_empty() {
+        vec![Line::styled(
+            "No parsed fields yet.",
+            Style::default().fg(Color::DarkGray),
+        )]
+    } else {
+        items
+    };
+    let list = ListSelect::new(items)
+        .titled(&title)
+        .hint(" Enter include  x exclude  f close ");
+    frame.render_stateful_widget(list, area, list_state);
+}
+
+/// Chart how many lines there are over time, by level, describing the selected bar in the title.
+fn render_histogram(
+    frame: &mut Frame,
+    area: Rect,
+    state: &AppState,
+    bar_state: &mut BarSelectState,
+) {
+    let histogram = &state.histogram;
+    let bars = histogram
+        .buckets
+        .iter()
+        .map(|bucket| {
+            HISTOGRAM_STACK
+                .iter()
+                .map(|&level| {
+                    let slot = level.map_or(SLOTS - 1, |level| level as usize);
+                    let color = match level {
+                        Some(level) => state.level_colors.get(level).unwrap_or(Color::Reset),
+                        None => Color::Gray,
+                    };
+                    (bucket.counts[slot], color)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut title = format!("Volume, {} per bar", duration_label(histogram.bucket_size));
+    if !histogram.is_timed() {
+        title.push_str(" by time read");
+    }
+    if let Some(bucket) = histogram.buckets.get(bar_state.selected) {
+        title.push_str(&format!(": {}", bucket_label(bucket)));
+    }
+    let total = state.lines.len();
+    if histogram.counted < total {
+        title.push_str(&format!(
+            " (counting {:.0}%)",
+            histogram.counted as f64 * 100.0 / total as f64
+        ));
+    }
+    let chart = BarSelect::new(bars)
+        .titled(&title)
+        .hint(" ←/→ jump  Enter jump and close  r restrict  c clear ");
+    frame.render_stateful_widget(chart, area, bar_state);
+}
+
+/// Describe a bar of the histogram, like `10:05:00..10:06:00 1200 lines, 3 error`.
+fn bucket_label(bucket: &Bucket) -> String {
+    let mut label = format!(
+        "{} {} lines",
+        time_range_label(bucket.start, bucket.end),
+        bucket.total()
+    );
+    for level in [Level::Fatal, Level::Error, Level::Warn] {
+        let count = bucket.counts[level as usize];
+        if count > 0 {
+            label.push_str(&format!(", {} {}", count, level.name().to_lowercase()));
+        }
+    }
+    label
+}
+
+/// A time range, leaving the date off the end if it's the same as the start's, and leaving out
+/// either end if the range is open there.
+fn time_range_label(start: NaiveDateTime, end: NaiveDateTime) -> String {
+    let same_day = end
+        .checked_sub_signed(TimeDelta::seconds(1))
+        .is_some_and(|last| last.date() == start.date());
+    let start = match start {
+        NaiveDateTime::MIN => String::new(),
+        start => start.format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    let end = match end {
+        NaiveDateTime::MAX => String::new(),
+        end if same_day => end.format("%H:%M:%S").to_string(),
+        end => end.format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    format!("{}..{}", start, end)
+}
+
+/// A number of seconds in the largest unit that divides it, like `15m`.
+fn duration_label(seconds: i64) -> String {
+    let unit = [(604800, "w"), (86400, "d"), (3600, "h"), (60, "m")]
+        .into_iter()
+        .find(|&(unit, _)| seconds >= unit && seconds % unit == 0);
+    match unit {
+        Some((unit, suffix)) => format!("{}{}", seconds / unit, suffix),
+        None => format!("{}s", seconds),
+    }
+}
+
+/// Describe the first source that failed to read, so the user knows the log is incomplete.
+fn error_status(sources: &[Source]) -> Option<Line<'static>> {
+    let (source, error) = sources
+        .iter()
+        .find_map(|source| Some((source, source.error.as_ref()?)))?;
+    let text = match sources {
+        [_] => format!(" {} ", error),
+        _ => format!(" {}: {} ", source.name, error),
+    };
+    Some(Line::styled(text, Style::default().fg(Color::Red)))
+}
+
+/// Mention the first configuration problem, and how many others there are. They're listed in
+/// full on exit.
+fn warning_status(warnings: &[String]) -> Option<Line<'static>> {
+    let (warning, rest) = warnings.split_first()?;
+    let warning = warning.lines().next().unwrap_or_default();
+    let text = match rest.len() {
+        0 => format!(" {} ", warning),
+        more => format!(" {} (and {} more) ", warning, more),
+    };
+    Some(Line::styled(text, Style::default().fg(Color::Yellow)))
+}
+
+fn search_status(search: &Search) -> String {
+    let matches = match (search.current, search.matches.len()) {
+        (_, 0) => format!("{}: no matches", search.pattern.source),
+        (Some(current), total) => format!(
+            "{}: match {} of {}",
+            search.pattern.source,
+            current + 1,
+            total
+        ),
+        (None, total) => format!("{}: {} matches", search.pattern.source, total),
+    };
+    match search.progress() {
+        Some(progress) => format!(" {}, searching {:.0}% ", matches, progress * 100.0),
+        None if search.is_cancelled() => format!(" {} (cancelled) ", matches),
+        None => format!(" {} ", matches),
+    }
+}
+
+/// List the lines matching the search, each with its line number. Only the lines that fit are
+/// built, since a search can match most of a large log.
+fn render_results(
+    frame: &mut Frame,
+    area: Rect,
+    state: &AppState,
+    list_state: &mut ListSelectState,
+) {
+    let Some(search) = state.search.as_ref() else {
+        return;
+    };
+
+    list_state.set_len(search.matches.len());
+    let window = list_state.window(area.height.saturating_sub(2) as usize);
+    let width = search.matches.last().map_or(1, |&index| {
+        (index + 1).checked_ilog10().unwrap_or(0) as usize + 1
+    });
+    let display_lines = search.matches[window.clone()]
+        .iter()
+        .filter_map(|&index| {
+            let line = state.lines.get_line(index)?;
+            let style = level_style(state, &line);
+            Some((
+                index,
+                DisplayLine::parse(&line.text, state.tab_width),
+                style,
+            ))
+        })
+        .collect::<Vec<_>>();
+    let items = display_lines
+        .iter()
+        .map(|(index, line, style)| {
+            let highlights = match_highlights(&search.pattern, line.text());
+            let mut spans = vec![Span::styled(
+                format!("{:>width$} ", index + 1),
+                Style::default().fg(Color::DarkGray),
+            )];
+            spans.extend(line.to_line(&highlights).spans);
+            Line::from(spans).style(*style)
+        })
+        .collect();
+
+    let mut title = match search.current {
+        Some(current) => format!("Results ({} of {})", current + 1, search.matches.len()),
+        None => format!("Results ({})", search.matches.len()),
+    };
+    if let Some(progress) = search.progress() {
+        title.push_str(&format!(", searching {:.0}%", progress * 100.0));
+    }
+    let list = ListSelect::new(items)
+        .titled(&title)
+        .hint(" Enter jump and close  R close ")
+        .window(window.start, search.matches.len());
+    frame.render_stateful_widget(list, area, list_state);
+}
+
+/// Show the JSON in the line the cursor is on as a tree, one field or array item per row.
+fn render_detail(
+    frame: &mut Frame,
+    area: Rect,
+    state: &AppState,
+    list_state: &mut ListSelectState,
+) {
+    let value = state.detail_value();
+    let nodes = value
+        .as_ref()
+        .map(|value| state.detail.nodes(value))
+        .unwrap_or_default();
+    let line_number = state.cursor.map_or(0, |index| index + 1);
+
+    let items = if value.is_none() {
+        vec![Line::styled(
+            format!("No JSON in line {}.", line_number),
+            Style::default().fg(Color::DarkGray),
+        )]
+    } else {
+        nodes
+            .iter()
+            .map(|node| {
+                let marker = match (node.is_container(), node.collapsed) {
+                    (false, _) => "  ",
+                    (true, false) => "▾ ",
+                    (true, true) => "▸ ",
+                };
+                let mut spans = vec![Span::raw("  ".repeat(node.depth)), Span::raw(marker)];
+                spans.push(match node.key {
+                    NodeKey::Field(key) => Span::styled(key.to_string(), Color::Cyan),
+                    NodeKey::Index(index) => Span::styled(index.to_string(), Color::DarkGray),
+                });
+                spans.push(Span::raw(": "));
+                spans.push(json_value_span(node.value, node.collapsed));
+                Line::from(spans)
+            })
+            .collect()
+    };
+
+    let mut title = format!("Line {}", line_number);
+    if let Some(node) = nodes.get(list_state.selected) {
+        title.push_str(&format!(" {}", node.path));
+    }
+    if let Some(copied) = state.detail.copied.as_ref() {
+        title.push_str(&format!(" (copied {})", copied));
+    }
+    let list = ListSelect::new(items)
+        .titled(&title)
+        .hint(" Space fold  y copy path  i/x filter value  J/K line  Enter close ");
+    frame.render_stateful_widget(list, area, list_state);
+}
+
+/// A JSON value colored by its type. Objects and arrays are summarized, since their contents
+/// are rows of their own.
+fn json_value_span(value: &Value, collapsed: bool) -> Span<'static> {
+    let dim = Style::default().fg(Color::DarkGray);
+    match value {
+        Value::Object(map) if collapsed => Span::styled(format!("{{…}} {} fields", map.len()), dim),
+        Value::Array(items) if collapsed => Span::styled(format!("[…] {} items", items.len()), dim),
+        Value::Object(_) => Span::styled("{", dim),
+        Value::Array(_) => Span::styled("[", dim),
+        Value::String(_) => Span::styled(value.to_string(), Color::Green),
+        Value::Number(_) => Span::styled(value.to_string(), Color::LightMagenta),
+        Value::Bool(_) | Value::Null => Span::styled(value.to_string(), Color::LightBlue),
+    }
+}
+
+/// The style of the line at buffer index `index` in the log view, which is its level's unless the
+/// cursor is on it.
+fn line_style(state: &AppState, index: usize, line: &LogLine) -> Style {
+    let style = level_style(state, line);
+    if state.cursor == Some(index) {
+        style.add_modifier(Modifier::REVERSED)
+    } else {
+        style
+    }
+}
+
+/// The style a line is shown in for its level. Colors from the line's own escape codes and
+/// search highlights are drawn over it.
+fn level_style(state: &AppState, line: &LogLine) -> Style {
+    let level = line.fields.as_ref().and_then(|fields| fields.level);
+    match level.and_then(|level| state.level_colors.get(level)) {
+        Some(color) => Style::default().fg(color),
+        None => Style::default(),
+    }
+}
+
+/// Styles for the matches of `pattern` in `line`, with each capture group in its own color.
+fn match_highlights(pattern: &Pattern, line: &str) -> Vec<(Range<usize>, Style)> {
+    pattern
+        .match_ranges(line)
+        .into_iter()
+        .map(|(group, range)| {
+            let color = match group {
+                0 => Color::Yellow,
+                group => GROUP_COLORS[(group - 1) % GROUP_COLORS.len()],
+            };
+            (range, Style::default().fg(Color::Black).bg(color))
+        })
+        .collect()
 }
 
 fn center_inside(area: Rect, width: u16, height: u16) -> Rect {
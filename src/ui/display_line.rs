@@ -0,0 +1,371 @@
+use std::{borrow::Cow, ops::Range};
+
+use ratatui::prelude::*;
+use unicode_width::UnicodeWidthChar;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// A log line made safe to draw. Raw lines can contain anything, including escape sequences that
+/// would move the cursor or retitle the terminal, so the only sequences that survive are SGR color
+/// and attribute codes, which become styles. Tabs are expanded, `\r` overwrites from the start of
+/// the line like it would on a terminal, and any other control character is shown escaped.
+pub struct DisplayLine {
+    text: String,
+    /// The byte offset in `text` where each style run starts, in order.
+    styles: Vec<(usize, Style)>,
+}
+
+/// The cells of a single terminal row, as written so far.
+#[derive(Default)]
+struct Screen {
+    cells: Vec<(char, Style)>,
+    cursor: usize,
+    column: usize,
+}
+
+impl Screen {
+    fn put(&mut self, ch: char, style: Style) {
+        match self.cells.get_mut(self.cursor) {
+            Some(cell) => *cell = (ch, style),
+            None => self.cells.push((ch, style)),
+        }
+        self.cursor += 1;
+        self.column += ch.width().unwrap_or(0);
+    }
+
+    /// Move to the next tab stop. Like a terminal, this moves over what is already there rather
+    /// than erasing it.
+    fn tab(&mut self, tab_width: usize, style: Style) {
+        let stop = (self.column / tab_width + 1) * tab_width;
+        while self.column < stop {
+            match self.cells.get(self.cursor) {
+                Some(&(ch, _)) => {
+                    self.column += ch.width().unwrap_or(0);
+                    self.cursor += 1;
+                }
+                None => self.put(' ', style),
+            }
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        self.cursor = 0;
+        self.column = 0;
+    }
+}
+
+impl DisplayLine {
+    pub fn parse(raw: &str, tab_width: usize) -> Self {
+        let mut screen = Screen::default();
+        let mut style = Style::default();
+        let control_style = Style::default().fg(Color::DarkGray);
+
+        let mut chars = raw.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                ESC => {
+                    if let Some(params) = skip_escape(&mut chars) {
+                        style = apply_sgr(style, &params);
+                    }
+                }
+                '\t' => screen.tab(tab_width.max(1), style),
+                '\r' => screen.carriage_return(),
+                ch if ch.is_control() => {
+                    for ch in escape_control(ch).chars() {
+                        screen.put(ch, control_style);
+                    }
+                }
+                ch => screen.put(ch, style),
+            }
+        }
+
+        let mut text = String::with_capacity(screen.cells.len());
+        let mut styles: Vec<(usize, Style)> = Vec::new();
+        for (ch, style) in screen.cells {
+            if styles.last().is_none_or(|&(_, last)| last != style) {
+                styles.push((text.len(), style));
+            }
+            text.push(ch);
+        }
+
+        Self { text, styles }
+    }
+
+    /// The text as it will be displayed, without any styling.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Convert to a styled line, patching each style in `highlights` over its byte range of
+    /// `text()`, in order.
+    pub fn to_line(&self, highlights: &[(Range<usize>, Style)]) -> Line<'_> {
+        let mut boundaries = self
+            .styles
+            .iter()
+            .map(|&(start, _)| start)
+            .chain(
+                highlights
+                    .iter()
+                    .flat_map(|(range, _)| [range.start, range.end]),
+            )
+            .chain([self.text.len()])
+            .collect::<Vec<_>>();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let spans = boundaries
+            .windows(2)
+            .map(|pair| {
+                let range = pair[0]..pair[1];
+                let run = self
+                    .styles
+                    .partition_point(|&(start, _)| start <= range.start);
+                let style = highlights
+                    .iter()
+                    .filter(|(h, _)| h.start <= range.start && range.end <= h.end)
+                    .fold(self.styles[run - 1].1, |style, (_, h)| style.patch(*h));
+                Span::styled(&self.text[range], style)
+            })
+            .collect::<Vec<_>>();
+
+        Line::from(spans)
+    }
+}
+
+/// The text `raw` is displayed as, which is what searches match against so that every match can
+/// be highlighted. Only lines with control characters need parsing; the rest are shown as is.
+pub fn display_text(raw: &str, tab_width: usize) -> Cow<'_, str> {
+    if raw.chars().any(char::is_control) {
+        Cow::Owned(DisplayLine::parse(raw, tab_width).text)
+    } else {
+        Cow::Borrowed(raw)
+    }
+}
+
+/// Consume an escape sequence whose `ESC` has already been read. Returns the parameters if it was
+/// an SGR sequence; everything else is dropped.
+fn skip_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    match chars.next()? {
+        // CSI: parameters and intermediates, then a final byte in `@`..=`~`.
+        '[' => {
+            let mut params = String::new();
+            for ch in chars.by_ref() {
+                if ('@'..='~').contains(&ch) {
+                    return (ch == 'm').then_some(params);
+                }
+                params.push(ch);
+            }
+            None
+        }
+        // OSC, DCS, SOS, PM and APC run until a string terminator (`ESC \`) or, for OSC, a bell.
+        ']' | 'P' | 'X' | '^' | '_' => {
+            while let Some(ch) = chars.next() {
+                if ch == BEL || (ch == ESC && chars.next_if_eq(&'\\').is_some()) {
+                    break;
+                }
+            }
+            None
+        }
+        // Anything else is intermediates (like the `(` in `ESC ( B`) and then one final character.
+        ch => {
+            if (' '..='/').contains(&ch) {
+                while chars.next_if(|&ch| (' '..='/').contains(&ch)).is_some() {}
+                chars.next();
+            }
+            None
+        }
+    }
+}
+
+/// Show a control character in caret notation (`^A`), or as a code point if it has none.
+fn escape_control(ch: char) -> String {
+    match ch as u32 {
+        code @ 0..=0x1f => format!("^{}", char::from(code as u8 + b'@')),
+        0x7f => "^?".to_string(),
+        code => format!("\\u{{{:02X}}}", code),
+    }
+}
+
+/// Update `style` with the SGR parameters of an `ESC [ ... m` sequence.
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let mut codes = params.split(';').map(|group| {
+        let mut parts = group.split(':');
+        // An empty code means 0; one that doesn't parse is kept out of range so it's ignored.
+        let code = match parts.next().unwrap_or("") {
+            "" => 0,
+            code => code.parse().unwrap_or(u16::MAX),
+        };
+        (
+            code,
+            parts.map(|part| part.parse().ok()).collect::<Vec<_>>(),
+        )
+    });
+
+    while let Some((code, sub_params)) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            6 => style.add_modifier(Modifier::RAPID_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            21 | 22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(basic_color(code - 30)),
+            39 => style.fg(Color::Reset),
+            40..=47 => style.bg(basic_color(code - 40)),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(bright_color(code - 90)),
+            100..=107 => style.bg(bright_color(code - 100)),
+            38 | 48 => {
+                // Extended colors come either as `38:5:n` in one parameter or `38;5;n` across several.
+                let args = if sub_params.is_empty() {
+                    extended_color_args(&mut codes)
+                } else {
+                    sub_params
+                };
+                match extended_color(&args) {
+                    Some(color) if code == 38 => style.fg(color),
+                    Some(color) => style.bg(color),
+                    None => style,
+                }
+            }
+            _ => style,
+        };
+    }
+
+    style
+}
+
+fn extended_color_args(
+    codes: &mut impl Iterator<Item = (u16, Vec<Option<u8>>)>,
+) -> Vec<Option<u8>> {
+    let mut next = || codes.next().and_then(|(n, _)| u8::try_from(n).ok());
+    match next() {
+        Some(5) => vec![Some(5), next()],
+        Some(2) => vec![Some(2), next(), next(), next()],
+        _ => Vec::new(),
+    }
+}
+
+fn extended_color(args: &[Option<u8>]) -> Option<Color> {
+    match args {
+        [Some(5), Some(n)] => Some(Color::Indexed(*n)),
+        [Some(2), Some(r), Some(g), Some(b)] => Some(Color::Rgb(*r, *g, *b)),
+        // The colon form may include a color space ID before the components.
+        [Some(2), _, Some(r), Some(g), Some(b), ..] => Some(Color::Rgb(*r, *g, *b)),
+        _ => None,
+    }
+}
+
+fn basic_color(index: u16) -> Color {
+    [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+    ][index as usize]
+}
+
+fn bright_color(index: u16) -> Color {
+    [
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ][index as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(line: &DisplayLine, highlights: &[Range<usize>]) -> Vec<(String, Style)> {
+        let highlight = Style::default().bg(Color::Yellow);
+        let highlights = highlights
+            .iter()
+            .map(|range| (range.clone(), highlight))
+            .collect::<Vec<_>>();
+        line.to_line(&highlights)
+            .spans
+            .into_iter()
+            .map(|span| (span.content.into_owned(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn test_sgr_colors() {
+        let line = DisplayLine::parse("\x1b[1;31mERROR\x1b[0m done \x1b[38;5;208mx\x1b[m", 8);
+        assert_eq!(line.text(), "ERROR done x");
+        assert_eq!(
+            runs(&line, &[]),
+            vec![
+                ("ERROR".to_string(), Style::default().fg(Color::Red).bold()),
+                (" done ".to_string(), Style::default()),
+                ("x".to_string(), Style::default().fg(Color::Indexed(208))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_sequences_are_stripped() {
+        let raw = "\x1b]0;pwned\x07a\x1b[2Jb\x1b]8;;http://x\x1b\\link\x1b]8;;\x1b\\\x1b(Bc";
+        assert_eq!(DisplayLine::parse(raw, 8).text(), "ablinkc");
+    }
+
+    #[test]
+    fn test_control_characters_are_escaped() {
+        assert_eq!(
+            DisplayLine::parse("a\x00b\x7fc\u{9b}", 8).text(),
+            "a^@b^?c\\u{9B}"
+        );
+    }
+
+    #[test]
+    fn test_tabs_and_carriage_returns() {
+        assert_eq!(DisplayLine::parse("a\tb\tc", 4).text(), "a   b   c");
+        assert_eq!(DisplayLine::parse("12345\t|", 4).text(), "12345   |");
+        assert_eq!(
+            DisplayLine::parse("progress 10%\rprogress 100%", 8).text(),
+            "progress 100%"
+        );
+        assert_eq!(DisplayLine::parse("abcdef\rXY", 8).text(), "XYcdef");
+        assert_eq!(DisplayLine::parse("abcdef\r\tX", 4).text(), "abcdXf");
+    }
+
+    #[test]
+    fn test_highlights_keep_line_styles() {
+        let line = DisplayLine::parse("\x1b[32mok\x1b[0m fine", 8);
+        assert_eq!(
+            runs(&line, &[1..4, 6..7]),
+            vec![
+                ("o".to_string(), Style::default().fg(Color::Green)),
+                (
+                    "k".to_string(),
+                    Style::default().fg(Color::Green).bg(Color::Yellow)
+                ),
+                (" f".to_string(), Style::default().bg(Color::Yellow)),
+                ("in".to_string(), Style::default()),
+                ("e".to_string(), Style::default().bg(Color::Yellow)),
+            ]
+        );
+    }
+}
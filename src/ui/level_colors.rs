@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+use ratatui::style::Color;
+
+use crate::format::Level;
+
+/// The color lines of each level are shown in. Levels without a color are shown as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelColors([Option<Color>; 6]);
+
+impl Default for LevelColors {
+    fn default() -> Self {
+        let mut colors = Self([None; 6]);
+        colors.set(Level::Trace, Some(Color::DarkGray));
+        colors.set(Level::Debug, Some(Color::DarkGray));
+        colors.set(Level::Warn, Some(Color::Yellow));
+        colors.set(Level::Error, Some(Color::Red));
+        colors.set(Level::Fatal, Some(Color::LightMagenta));
+        colors
+    }
+}
+
+impl LevelColors {
+    pub fn get(&self, level: Level) -> Option<Color> {
+        self.0[level as usize]
+    }
+
+    pub fn set(&mut self, level: Level, color: Option<Color>) {
+        self.0[level as usize] = color;
+    }
+}
+
+/// Parse a `LEVEL=COLOR` setting, where the color is a name like `red` or `light-blue`, a hex
+/// code like `#ff8800`, a 256-color palette index, or `none` to leave the level uncolored.
+pub fn parse_level_color(setting: &str) -> Result<(Level, Option<Color>), String> {
+    let (level, color) = setting
+        .split_once('=')
+        .ok_or_else(|| format!("expected LEVEL=COLOR, got `{}`", setting))?;
+    let level = Level::parse(level).ok_or_else(|| format!("unknown level `{}`", level))?;
+    let color = match color.trim() {
+        "none" => None,
+        color => Some(Color::from_str(color).map_err(|_| format!("unknown color `{}`", color))?),
+    };
+    Ok((level, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_color() {
+        assert_eq!(
+            parse_level_color("warning=#ff8800"),
+            Ok((Level::Warn, Some(Color::Rgb(0xff, 0x88, 0x00))))
+        );
+        assert_eq!(parse_level_color("debug=none"), Ok((Level::Debug, None)));
+        assert_eq!(
+            parse_level_color("info=light-blue"),
+            Ok((Level::Info, Some(Color::LightBlue)))
+        );
+        assert!(parse_level_color("info").is_err());
+        assert!(parse_level_color("loud=red").is_err());
+        assert!(parse_level_color("info=plaid").is_err());
+    }
+}
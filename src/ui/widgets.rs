@@ -1 +1,3 @@
+pub mod bar_select;
+pub mod list_select;
 pub mod text_input;
@@ -0,0 +1,175 @@
+use std::error::Error;
+
+use crossterm::event::{self, Event, KeyCode};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, StatefulWidget, Widget},
+};
+
+use crate::ui::focusable_input::InputHandler;
+
+/// Characters for the top of a bar that only partly fills a cell, by eighths.
+const PARTIAL_BLOCKS: [&str; 8] = [" ", "▁", "▂", "▃", "▄", "▅", "▆", "▇"];
+
+#[derive(Debug, Clone)]
+pub enum BarSelectMsg {
+    Close,
+    /// The bar at this index was moved to.
+    Select(usize),
+    /// Enter was pressed on the bar at this index.
+    Accept(usize),
+    /// A key without a meaning to the chart itself was pressed with the bar at this index selected.
+    Key(usize, char),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BarSelectState {
+    pub selected: usize,
+    /// Number of bars, as of the last render.
+    pub len: usize,
+}
+
+impl BarSelectState {
+    pub fn new(selected: usize) -> Self {
+        Self { selected, len: 0 }
+    }
+
+    fn select(&mut self, index: usize) -> Option<BarSelectMsg> {
+        let index = index.min(self.len.saturating_sub(1));
+        if index == self.selected {
+            return None;
+        }
+        self.selected = index;
+        Some(BarSelectMsg::Select(index))
+    }
+}
+
+impl InputHandler for BarSelectState {
+    type Message = BarSelectMsg;
+
+    fn handle_input(&mut self) -> Result<Option<BarSelectMsg>, Box<dyn Error>> {
+        let Some(Event::Key(key)) = self.get_next_event()? else {
+            return Ok(None);
+        };
+        let ctrl = key.modifiers.contains(event::KeyModifiers::CONTROL);
+
+        let msg = match key.code {
+            KeyCode::Esc => Some(BarSelectMsg::Close),
+            KeyCode::Char('c') if ctrl => Some(BarSelectMsg::Close),
+            KeyCode::Enter => Some(BarSelectMsg::Accept(self.selected)),
+
+            KeyCode::Left | KeyCode::Char('h') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => self.select(self.selected + 1),
+            KeyCode::Home => self.select(0),
+            KeyCode::End => self.select(self.len.saturating_sub(1)),
+
+            KeyCode::Char(c) => Some(BarSelectMsg::Key(self.selected, c)),
+            _ => None,
+        };
+
+        Ok(msg)
+    }
+}
+
+/// A chart of stacked bars, one column wide each, with one of them selected.
+#[derive(Debug, Clone, Default)]
+pub struct BarSelect {
+    pub title: Option<String>,
+    /// The segments of each bar from the bottom up, as amounts and the color to draw them in.
+    pub bars: Vec<Vec<(usize, Color)>>,
+    /// Shown along the bottom border, e.g. to list the keys the chart responds to.
+    pub hint: Option<String>,
+}
+
+impl BarSelect {
+    pub fn new(bars: Vec<Vec<(usize, Color)>>) -> Self {
+        Self {
+            bars,
+            ..Default::default()
+        }
+    }
+
+    pub fn titled(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+}
+
+impl StatefulWidget for BarSelect {
+    type State = BarSelectState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        state.len = self.bars.len();
+        state.selected = state.selected.min(state.len.saturating_sub(1));
+
+        Clear.render(area, buf);
+        let mut block = Block::default()
+            .title(self.title.unwrap_or_default())
+            .borders(Borders::ALL);
+        if let Some(hint) = self.hint {
+            block = block.title_bottom(hint);
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let max = self
+            .bars
+            .iter()
+            .map(|bar| bar.iter().map(|&(amount, _)| amount).sum::<usize>())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let rows = inner.height as usize;
+
+        for (index, bar) in self.bars.iter().enumerate().take(inner.width as usize) {
+            let x = inner.x + index as u16;
+            if index == state.selected {
+                for y in inner.top()..inner.bottom() {
+                    buf[(x, y)].set_bg(Color::DarkGray);
+                }
+            }
+
+            // The top of each segment, in eighths of a cell. Every non-empty bar gets at least an
+            // eighth so that quiet periods can be told apart from gaps.
+            let mut tops = Vec::with_capacity(bar.len());
+            let mut total = 0;
+            for &(amount, color) in bar {
+                total += amount;
+                let top = (total * rows * 8).div_ceil(max);
+                tops.push((top, color));
+            }
+
+            let bar_top = tops.last().map_or(0, |&(top, _)| top);
+            for row in 0..rows {
+                let y = inner.bottom() - 1 - row as u16;
+                let (bottom, top) = (row * 8, row * 8 + 8);
+                if bar_top <= bottom {
+                    break;
+                }
+                // The cell takes the color of the segment in the middle of its filled part.
+                let middle = (bottom + bar_top.min(top)) / 2;
+                let Some(&(_, color)) = tops.iter().find(|&&(segment_top, _)| segment_top > middle)
+                else {
+                    continue;
+                };
+                let symbol = if bar_top >= top {
+                    "█"
+                } else {
+                    PARTIAL_BLOCKS[bar_top - bottom]
+                };
+                let mut style = Style::default().fg(color);
+                if index == state.selected {
+                    style = style.add_modifier(Modifier::BOLD);
+                }
+                buf[(x, y)].set_symbol(symbol).set_style(style);
+            }
+        }
+    }
+}
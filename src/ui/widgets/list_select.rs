@@ -0,0 +1,164 @@
+use std::{error::Error, ops::Range};
+
+use crossterm::event::{self, Event, KeyCode};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget},
+};
+
+use crate::ui::focusable_input::InputHandler;
+
+#[derive(Debug, Clone)]
+pub enum ListSelectMsg {
+    Close,
+    /// The item at this index was moved to.
+    Select(usize),
+    /// Enter was pressed on the item at this index.
+    Accept(usize),
+    /// A key without a meaning to the list itself was pressed with the item at this index selected.
+    Key(usize, char),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListSelectState {
+    pub selected: usize,
+    /// Number of items, as of the last render.
+    pub len: usize,
+    /// Number of items that fit in the list, as of the last render.
+    pub page: usize,
+}
+
+impl ListSelectState {
+    pub fn new(selected: usize) -> Self {
+        Self {
+            selected,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+
+    /// The range of items that are shown when `height` of them fit, which keeps the selected
+    /// item in view.
+    pub fn window(&self, height: usize) -> Range<usize> {
+        let start = (self.selected + 1).saturating_sub(height);
+        start..(start + height).min(self.len)
+    }
+
+    fn select(&mut self, index: usize) -> Option<ListSelectMsg> {
+        let index = index.min(self.len.saturating_sub(1));
+        if index == self.selected {
+            return None;
+        }
+        self.selected = index;
+        Some(ListSelectMsg::Select(index))
+    }
+}
+
+impl InputHandler for ListSelectState {
+    type Message = ListSelectMsg;
+
+    fn handle_input(&mut self) -> Result<Option<ListSelectMsg>, Box<dyn Error>> {
+        let Some(Event::Key(key)) = self.get_next_event()? else {
+            return Ok(None);
+        };
+        let ctrl = key.modifiers.contains(event::KeyModifiers::CONTROL);
+
+        let msg = match key.code {
+            KeyCode::Esc => Some(ListSelectMsg::Close),
+            KeyCode::Char('c') if ctrl => Some(ListSelectMsg::Close),
+            KeyCode::Enter => Some(ListSelectMsg::Accept(self.selected)),
+
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected + 1),
+            KeyCode::PageUp => self.select(self.selected.saturating_sub(self.page)),
+            KeyCode::PageDown => self.select(self.selected + self.page),
+            KeyCode::Home => self.select(0),
+            KeyCode::End => self.select(self.len.saturating_sub(1)),
+
+            KeyCode::Char(c) => Some(ListSelectMsg::Key(self.selected, c)),
+            _ => None,
+        };
+
+        Ok(msg)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListSelect<'a> {
+    pub title: Option<String>,
+    pub items: Vec<Line<'a>>,
+    /// Index of the first item in `items`, for lists too long to build every item of. Only the
+    /// items in the state's `window` need to be given.
+    pub first: usize,
+    /// Total number of items, if not all of them are in `items`.
+    pub len: Option<usize>,
+    /// Shown along the bottom border, e.g. to list the keys the list responds to.
+    pub hint: Option<String>,
+}
+
+impl<'a> ListSelect<'a> {
+    pub fn new(items: Vec<Line<'a>>) -> Self {
+        Self {
+            items,
+            ..Default::default()
+        }
+    }
+
+    pub fn titled(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+
+    /// Say that `items` starts at the item at index `first`, out of `len` in total.
+    pub fn window(mut self, first: usize, len: usize) -> Self {
+        self.first = first;
+        self.len = Some(len);
+        self
+    }
+}
+
+impl StatefulWidget for ListSelect<'_> {
+    type State = ListSelectState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        state.set_len(self.len.unwrap_or(self.items.len()));
+
+        Clear.render(area, buf);
+        let mut block = Block::default()
+            .title(self.title.unwrap_or_default())
+            .borders(Borders::ALL);
+        if let Some(hint) = self.hint {
+            block = block.title_bottom(hint);
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        state.page = inner.height as usize;
+        let window = state.window(inner.height as usize);
+        let items = (self.first..)
+            .zip(self.items)
+            .filter(|(index, _)| window.contains(index))
+            .map(|(index, item)| {
+                if index == state.selected {
+                    item.patch_style(Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    item
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(items).render(inner, buf);
+    }
+}
@@ -1,10 +1,10 @@
 use std::error::Error;
 
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyEvent};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::Color,
+    style::{Color, Style},
     text::Text,
     widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget, Wrap},
 };
@@ -17,6 +17,8 @@ pub enum TextInputMsg {
     Close,
     Accept(String),
     Change(String),
+    /// A character typed while holding Alt, which prompts can use for their own shortcuts.
+    Alt(char),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -24,6 +26,29 @@ pub struct TextInputState {
     pub input: Input,
     pub cursor_position: (u16, u16),
     pub color: Color,
+    /// Earlier inputs, oldest first, that can be brought back with Up/Down and Ctrl+r.
+    history: Vec<String>,
+    /// The entry in `history` being shown, if any.
+    history_index: Option<usize>,
+    /// What had been typed before going back through the history.
+    draft: String,
+    pub reverse_search: Option<ReverseSearch>,
+    /// Words that Tab completes the word before the cursor to.
+    completions: Vec<String>,
+    /// The completions that fit the word before the cursor, after a Tab that fit more than one.
+    pub candidates: Vec<String>,
+    /// A problem with the input and the character it's at, which is pointed out under it.
+    pub error: Option<(usize, String)>,
+}
+
+/// A Ctrl+r search back through the history, like the one in readline.
+#[derive(Debug, Clone, Default)]
+pub struct ReverseSearch {
+    pub query: String,
+    /// The entry in the history that matches the query, if any.
+    pub found: Option<usize>,
+    /// The input from before the search, for if it's cancelled.
+    original: String,
 }
 
 impl TextInputState {
@@ -34,6 +59,147 @@ impl TextInputState {
             ..Default::default()
         }
     }
+
+    pub fn with_history(mut self, history: &[String]) -> Self {
+        self.history = history.to_vec();
+        self
+    }
+
+    pub fn with_completions(mut self, completions: Vec<String>) -> Self {
+        self.completions = completions;
+        self
+    }
+
+    /// Complete the word before the cursor as far as the completions that fit it agree, listing
+    /// them if there's more than one.
+    fn complete(&mut self) -> Option<TextInputMsg> {
+        let mut word = self
+            .input
+            .value()
+            .chars()
+            .take(self.input.cursor())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .take_while(|&c| c.is_alphanumeric() || "_.-@".contains(c))
+            .collect::<Vec<_>>();
+        word.reverse();
+        let word = word.into_iter().collect::<String>();
+
+        let candidates = self
+            .completions
+            .iter()
+            .filter(|completion| completion.starts_with(&word))
+            .cloned()
+            .collect::<Vec<_>>();
+        let first = candidates.first()?;
+        let common = candidates.iter().fold(first.as_str(), |common, candidate| {
+            let len = common
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(candidate.len()), |((i, _), _)| i);
+            &common[..len]
+        });
+
+        let mut insert = common[word.len()..].to_string();
+        if candidates.len() == 1 {
+            insert.push(' ');
+        }
+        self.candidates = if candidates.len() > 1 {
+            candidates
+        } else {
+            Vec::new()
+        };
+        if insert.is_empty() {
+            return None;
+        }
+        for char in insert.chars() {
+            self.input.handle(InputRequest::InsertChar(char));
+        }
+        Some(TextInputMsg::Change(self.input.value().to_string()))
+    }
+
+    fn set_value(&mut self, value: String) -> Option<TextInputMsg> {
+        self.input = Input::new(value);
+        Some(TextInputMsg::Change(self.input.value().to_string()))
+    }
+
+    fn history_up(&mut self) -> Option<TextInputMsg> {
+        let index = match self.history_index {
+            Some(0) => return None,
+            Some(index) => index - 1,
+            None => {
+                self.draft = self.input.value().to_string();
+                self.history.len().checked_sub(1)?
+            }
+        };
+        self.history_index = Some(index);
+        self.set_value(self.history[index].clone())
+    }
+
+    fn history_down(&mut self) -> Option<TextInputMsg> {
+        let index = self.history_index?;
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            self.set_value(self.history[index + 1].clone())
+        } else {
+            self.history_index = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_value(draft)
+        }
+    }
+
+    /// The newest entry before `before` that contains `query`.
+    fn find_in_history(&self, query: &str, before: usize) -> Option<usize> {
+        self.history[..before.min(self.history.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    fn handle_reverse_search(&mut self, key: KeyEvent) -> Option<TextInputMsg> {
+        let ctrl = key.modifiers.contains(event::KeyModifiers::CONTROL);
+        let search = self.reverse_search.as_mut()?;
+
+        let before = match key.code {
+            KeyCode::Char('r') if ctrl => search.found.unwrap_or(self.history.len()),
+            KeyCode::Char('c' | 'g') if ctrl => {
+                let original = self.reverse_search.take()?.original;
+                return self.set_value(original);
+            }
+            KeyCode::Esc => {
+                let original = self.reverse_search.take()?.original;
+                return self.set_value(original);
+            }
+            KeyCode::Enter => {
+                self.reverse_search = None;
+                return Some(TextInputMsg::Accept(self.input.value().to_string()));
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.history.len()
+            }
+            KeyCode::Char(char) if !ctrl => {
+                search.query.push(char);
+                // The entry that's shown may still match with the extra character.
+                search.found.map_or(self.history.len(), |index| index + 1)
+            }
+            // Any other key leaves the search with the entry that was found.
+            _ => {
+                self.reverse_search = None;
+                return None;
+            }
+        };
+
+        let query = search.query.clone();
+        let found = self.find_in_history(&query, before);
+        let search = self.reverse_search.as_mut()?;
+        search.found = found;
+        // Without a match, the last entry that matched stays in the input, like in readline.
+        let index = found?;
+        self.history_index = None;
+        self.set_value(self.history[index].clone())
+    }
 }
 
 impl InputHandler for TextInputState {
@@ -48,13 +214,34 @@ impl InputHandler for TextInputState {
         let event = event.unwrap();
 
         if let Event::Key(key) = event {
+            if self.reverse_search.is_some() {
+                return Ok(self.handle_reverse_search(key));
+            }
+
+            if key.code != KeyCode::Tab {
+                self.candidates.clear();
+            }
+
             let modifiers = key.modifiers;
             let ctrl = modifiers.contains(event::KeyModifiers::CONTROL);
+            let alt = modifiers.contains(event::KeyModifiers::ALT);
 
             let msg = match key.code {
                 KeyCode::Esc => Some(TextInputMsg::Close),
                 KeyCode::Enter => Some(TextInputMsg::Accept(self.input.value().to_string())),
                 KeyCode::Char('c') if ctrl => Some(TextInputMsg::Close),
+                KeyCode::Char(char) if alt => Some(TextInputMsg::Alt(char)),
+
+                KeyCode::Tab => self.complete(),
+                KeyCode::Up => self.history_up(),
+                KeyCode::Down => self.history_down(),
+                KeyCode::Char('r') if ctrl => {
+                    self.reverse_search = Some(ReverseSearch {
+                        original: self.input.value().to_string(),
+                        ..Default::default()
+                    });
+                    None
+                }
 
                 KeyCode::Backspace => {
                     self.input.handle(InputRequest::DeletePrevChar);
@@ -160,13 +347,24 @@ impl StatefulWidget for TextInput {
         extended_by_one.x = extended_by_one.x.saturating_sub(1);
         extended_by_one.width += 2;
 
-        Clear.render(extended_by_one, buf);
-        let search_block = Block::default()
+        Clear.render(extended_by_one.intersection(*buf.area()), buf);
+        let mut search_block = Block::default()
             .title(self.title.as_deref().unwrap_or(""))
             .borders(Borders::ALL);
+        if let Some(search) = state.reverse_search.as_ref() {
+            let status = match search.found {
+                Some(_) => format!(" reverse search: {} ", search.query),
+                None if search.query.is_empty() => " reverse search: ".to_string(),
+                None => format!(" no match: {} ", search.query),
+            };
+            search_block = search_block.title_bottom(status);
+        } else if !state.candidates.is_empty() {
+            search_block = search_block.title_bottom(format!(" {} ", state.candidates.join("  ")));
+        }
         search_block.render(area, buf);
 
-        let text_area = Rect::new(area.x + 2, area.y + 1, area.width - 2, 1);
+        let text_area =
+            Rect::new(area.x + 2, area.y + 1, area.width.saturating_sub(2), 1).intersection(area);
         let search_text =
             Paragraph::new(Text::from(state.input.to_string().to_owned()).style(state.color))
                 .wrap(Wrap { trim: false });
@@ -177,5 +375,105 @@ impl StatefulWidget for TextInput {
             text_area.y,
         );
         state.cursor_position = cursor_position;
+
+        if let (Some((column, message)), true) = (state.error.as_ref(), state.candidates.is_empty())
+        {
+            let x = text_area.x + *column as u16;
+            let width = area.right().saturating_sub(x + 1) as usize;
+            // Too narrow to point at anything.
+            if width == 0 {
+                return;
+            }
+            buf.set_stringn(
+                x,
+                area.bottom() - 1,
+                format!("^ {}", message),
+                width,
+                Style::default().fg(Color::Red),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn history() -> Vec<String> {
+        ["error", "timeout", "api error", "db"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn test_history_recall() {
+        let mut state = TextInputState::from_str("draft").with_history(&history());
+        state.history_up();
+        state.history_up();
+        assert_eq!(state.input.value(), "api error");
+        state.history_down();
+        assert_eq!(state.input.value(), "db");
+        state.history_down();
+        assert_eq!(state.input.value(), "draft");
+        assert!(state.history_down().is_none());
+    }
+
+    #[test]
+    fn test_completion() {
+        let completions = ["level", "latency_ms", "latency_p99", "message"]
+            .map(String::from)
+            .to_vec();
+        let mut state =
+            TextInputState::from_str("level = warn and la").with_completions(completions);
+        state.complete();
+        assert_eq!(state.input.value(), "level = warn and latency_");
+        assert_eq!(state.candidates, ["latency_ms", "latency_p99"]);
+        state.input.handle(InputRequest::InsertChar('m'));
+        state.complete();
+        assert_eq!(state.input.value(), "level = warn and latency_ms ");
+        assert!(state.candidates.is_empty());
+        assert!(state.complete().is_none());
+        assert_eq!(state.candidates.len(), 4);
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        let mut state = TextInputState::from_str("draft").with_history(&history());
+        state.reverse_search = Some(ReverseSearch {
+            original: "draft".to_string(),
+            ..Default::default()
+        });
+
+        for c in "err".chars() {
+            state.handle_reverse_search(key(KeyCode::Char(c)));
+        }
+        assert_eq!(state.input.value(), "api error");
+        state.handle_reverse_search(ctrl_r);
+        assert_eq!(state.input.value(), "error");
+
+        // Nothing older matches, so the last match stays.
+        state.handle_reverse_search(ctrl_r);
+        assert_eq!(state.input.value(), "error");
+        assert!(state.reverse_search.as_ref().unwrap().found.is_none());
+
+        state.handle_reverse_search(key(KeyCode::Esc));
+        assert!(state.reverse_search.is_none());
+        assert_eq!(state.input.value(), "draft");
+    }
+
+    #[test]
+    fn test_render_narrower_than_borders() {
+        let mut state = TextInputState::from_str("14:00");
+        state.error = Some((0, "expected a time".to_string()));
+        for width in 0..4 {
+            let area = Rect::new(0, 0, width, 3);
+            let mut buf = Buffer::empty(area);
+            TextInput::default()
+                .titled("Line #")
+                .render(area, &mut buf, &mut state);
+        }
     }
 }